@@ -0,0 +1,76 @@
+//! Benchmarks of searching a synthetic corpus, run with `cargo bench`. Files are searched one by
+//! one, so that the cost of going through the lines is measured, not the parallelism.
+
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use structopt::StructOpt;
+
+use marks::args::Args;
+use marks::marks::Marks;
+
+const FILES: usize = 100;
+const SECTIONS: usize = 40;
+const LINES: usize = 25;
+
+/// Write the corpus into a temporary directory. Sections are nested up to three levels deep, and
+/// every line has some markup and a link.
+fn corpus() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("marks-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for file in 0..FILES {
+        let mut text = String::new();
+        for section in 0..SECTIONS {
+            let stars = "*".repeat(section % 3 + 1);
+            text.push_str(&format!(
+                "{} Section {} of file {} :tag{}:\n",
+                stars,
+                section,
+                file,
+                section % 5
+            ));
+            for line in 0..LINES {
+                text.push_str(&format!(
+                    "Line {} with some *words*, a [[file:other.org][link]] and a number {}\n",
+                    line,
+                    (file * section + line) % 97
+                ));
+            }
+        }
+        std::fs::write(dir.join(format!("{}.org", file)), text).unwrap();
+    }
+
+    dir
+}
+
+fn search(c: &mut Criterion, dir: &Path, name: &str, query: &str) {
+    let args = Args::from_iter(["marks", "--no-index", query, dir.to_str().unwrap()]);
+    let app = Marks::new(&args);
+    let files = app.find_files().map(|x| x.into_path()).collect::<Vec<_>>();
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            files
+                .iter()
+                .filter_map(|x| app.search_file(x))
+                .map(|x| x.results.len())
+                .sum::<usize>()
+        })
+    });
+}
+
+fn benches(c: &mut Criterion) {
+    let dir = corpus();
+    search(c, &dir, "no hits", "\"nowhere\"");
+    search(c, &dir, "rare hits", "\"number 42\"");
+    search(c, &dir, "many hits", "words");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+criterion_group! {
+    name = search_benches;
+    config = Criterion::default().sample_size(10);
+    targets = benches
+}
+criterion_main!(search_benches);
@@ -1,6 +1,6 @@
 #!/bin/sh
 INITIAL_QUERY=""
-RG_PREFIX="marks --no-markdown --path ~/ --query "
+RG_PREFIX="PWD=$HOME marks --no-markdown -- "
 FZF_DEFAULT_COMMAND="$RG_PREFIX '$INITIAL_QUERY'" \
   fzf --bind "change:reload:$RG_PREFIX {q} || true" \
       --ansi --phony --query "$INITIAL_QUERY" \
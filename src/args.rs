@@ -1,13 +1,19 @@
-use std::{error::Error, path::PathBuf};
+use std::{error::Error, ffi::OsString, path::PathBuf};
+
 use combine::Parser;
+use ignore::overrides::{Override, OverrideBuilder};
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
 
-use crate::{org::{datetime::{OrgDatePlan, OrgDateTime}, header::{OrgPriority, OrgTodo}}, parsers, query::Query};
+use crate::{boost, formats, org::{datetime::{OrgDatePlan, OrgDateTime}, header::{OrgPriority, OrgTodo, PriorityRange}}, parsers, query::Query};
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "marks")]
+#[structopt(name = "marks", setting = AppSettings::SubcommandsNegateReqs)]
 /// A search-engine like search tool for markdown and org-mode files.
 pub struct Args {
+    #[structopt(subcommand)]
+    pub cmd: Option<Command>,
+
     /// Activate debug mode
     #[structopt(short, long)]
     pub debug: bool,
@@ -20,7 +26,7 @@ pub struct Args {
     #[structopt(long, parse(try_from_str = parse_todos))]
     pub todo: Vec<OrgTodo>,
 
-    /// List of priorities. Note that items without priorites will not match if you use this option.
+    /// List of priorities. Headers without a priority have the default priority, see --priorities.
     #[structopt(long, parse(try_from_str = parse_priority))]
     pub priority: Vec<OrgPriority>,
 
@@ -32,6 +38,11 @@ pub struct Args {
     #[structopt(long, parse(try_from_str = parse_priority))]
     pub priority_gt: Option<OrgPriority>,
 
+    /// The highest, the lowest and the default priority, like "A C B" or "1 10 5". Files can
+    /// override it with #+PRIORITIES:.
+    #[structopt(long, env = "MARKS_PRIORITIES", default_value = "A C B", parse(try_from_str = PriorityRange::parse))]
+    pub priorities: PriorityRange,
+
     /// Scheduled date.
     #[structopt(long, parse(try_from_str = parse_org_scheduled))]
     pub scheduled_at: Option<OrgDateTime>,
@@ -73,6 +84,23 @@ pub struct Args {
     #[structopt(long)]
     pub no_markdown: bool,
 
+    /// Only search files of the given type. Can be given multiple times, see --type-list for the
+    /// supported types. Org and markdown files are searched by default.
+    #[structopt(short = "t", long = "type", number_of_values = 1, parse(try_from_str = parse_type))]
+    pub types: Vec<String>,
+
+    /// Don't search files of the given type.
+    #[structopt(short = "T", long = "type-not", number_of_values = 1, parse(try_from_str = parse_type))]
+    pub types_not: Vec<String>,
+
+    /// Search code cells of Jupyter notebooks too, not only the markdown cells.
+    #[structopt(long)]
+    pub notebook_code: bool,
+
+    /// Print the supported file types and their extensions, then exit.
+    #[structopt(long)]
+    pub type_list: bool,
+
     /// Whether to search in files too.
     #[structopt(long)]
     pub search_filename: bool,
@@ -93,10 +121,122 @@ pub struct Args {
     #[structopt(long, default_value = "/")]
     pub header_seperator: String,
 
+    /// Search the subtrees tagged with ARCHIVE and the archive files, like x.org_archive, too.
+    #[structopt(long)]
+    pub include_archived: bool,
+
+    /// Search the COMMENT subtrees too.
+    #[structopt(long)]
+    pub include_comments: bool,
+
+    /// Search the subtrees tagged with noexport too.
+    #[structopt(long)]
+    pub include_noexport: bool,
+
+    /// Don't search the contents of drawers, like :LOGBOOK: ... :END:.
+    #[structopt(long)]
+    pub no_drawers: bool,
+
+    /// Search the contents of the files included with #+INCLUDE: as if they were in the including
+    /// file. Results still show the file and the line the contents come from.
+    #[structopt(long)]
+    pub follow_includes: bool,
+
+    /// Print the definitions of the footnotes that are referenced in the results, under the
+    /// results.
+    #[structopt(long)]
+    pub footnotes: bool,
+
+    /// How to rank the results: fuzzy, by the fuzzy match scores of the terms, or bm25, by how
+    /// often the terms appear in the sections of the results and how rare they are in all sections.
+    #[structopt(long, default_value = "fuzzy", parse(try_from_str = parse_rank))]
+    pub rank: Rank,
+
+    /// How to match the fuzzy terms of the query: skim, fzf (like fzf's default algorithm),
+    /// substring or word (the term has to be a whole word in the line).
+    #[structopt(long, default_value = "skim", parse(try_from_str = parse_matcher))]
+    pub matcher: MatcherKind,
+
+    /// Case sensitivity of the fuzzy terms: smart (sensitive only if the term has an uppercase
+    /// letter), ignore or respect.
+    #[structopt(long, default_value = "smart", parse(try_from_str = parse_case))]
+    pub case: CaseMode,
+
+    /// Only accept fuzzy matches that start at the beginning of a word.
+    #[structopt(long)]
+    pub word_boundary: bool,
+
+    /// Drop the fuzzy matches that score lower than this.
+    #[structopt(long)]
+    pub min_score: Option<i64>,
+
+    /// How to order the results: score, path, line, mtime (newest files first), scheduled or
+    /// deadline (earliest dates first), priority (highest first) or todo (TODO, other keywords,
+    /// DONE). Results that are equal are ordered by their path and line. none prints the results
+    /// as soon as they are found and stops searching once --count results are printed.
+    #[structopt(long, default_value = "score", parse(try_from_str = parse_sort))]
+    pub sort: SortKey,
+
+    /// Reverse the order of the results.
+    #[structopt(long)]
+    pub reverse: bool,
+
+    /// Change how much a boost counts, like header=2 or recency=0.5. Boosts multiply the scores of
+    /// the results: header (the query matches the header of the section), depth (shallower
+    /// sections), recency (the CREATED property or the modification time of the file), priority
+    /// and deadline (the deadline is close). header=1 and depth=0.5 are on by default, a weight of
+    /// 0 turns a boost off.
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_boost))]
+    pub boost: Vec<(String, f64)>,
+
+    /// Print why each result matched and how its score is calculated, under the result.
+    #[structopt(long)]
+    pub explain: bool,
+
+    /// Search only the given file and print why each of its sections was skipped, along with the
+    /// explained results.
+    #[structopt(long)]
+    pub explain_file: Option<PathBuf>,
+
+    /// Match and print whole blocks, like source blocks, instead of their lines one by one.
+    #[structopt(long)]
+    pub whole_blocks: bool,
+
+    /// What to match the query against: every line separately, whole paragraphs or whole
+    /// sections (a header and its content until the next header).
+    #[structopt(long, default_value = "line", parse(try_from_str = parse_unit))]
+    pub unit: SearchUnit,
+
+    /// Don't use the index built with `marks index`, always walk the tree and read every file.
+    #[structopt(long)]
+    pub no_index: bool,
+
+    /// Where to keep the index. Defaults to $XDG_CACHE_HOME/marks or ~/.cache/marks.
+    #[structopt(long, env = "MARKS_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+
     /// List folder names to blacklist
     #[structopt(long)]
     pub blacklist_folder: Vec<String>,
 
+    /// Search the files that are excluded by .gitignore, .ignore and .marksignore files.
+    #[structopt(long)]
+    pub no_ignore: bool,
+
+    /// Search the hidden files and directories, whose names start with a dot.
+    #[structopt(long)]
+    pub hidden: bool,
+
+    /// Only search the files whose paths match the glob, or skip them if the glob starts with
+    /// `!`. Globs are matched against the paths under the searched directory, like
+    /// `projects/**/*.org` or `!**/archive/**`. Can be given multiple times.
+    #[structopt(long, number_of_values = 1)]
+    pub glob: Vec<String>,
+
+    /// Same as --glob, but ignores the case of the paths.
+    #[structopt(long, number_of_values = 1)]
+    pub iglob: Vec<String>,
+
     /// The query.
     ///
     /// An example query may look like this:
@@ -110,14 +250,227 @@ pub struct Args {
     ///   - "badword" to be not in the title hierarchy or the line itself.
     ///
     /// Rest of the characters are matched in fuzzy fashion.
-    #[structopt(parse(try_from_str = parse_query), required=true, verbatim_doc_comment)]
-    pub query: Query,
+    ///
+    /// Following terms can also be used:
+    ///
+    ///   - link:target  only match lines that have a link whose target contains "target".
+    ///   - col:Name=value  only match table rows whose "Name" column is "value".
+    ///   - drawer:NAME  only match lines inside of :NAME: ... :END: drawers.
+    ///   - lang:python  only match lines inside of python source blocks.
+    ///   - tangle:file  only match lines inside of blocks that are tangled to "file". tangle:
+    ///     matches blocks that are tangled anywhere.
+    ///   - block:name  only match lines inside of the block named with #+NAME: name.
+    ///   - arg:key=value  only match lines inside of blocks whose :key header argument contains
+    ///     "value". arg:key matches blocks that have the argument.
+    ///
+    /// A query that is the name of a subcommand runs the subcommand, give it after `--` to search
+    /// for it instead: marks -- graph
+    #[structopt(parse(try_from_str = parse_query), verbatim_doc_comment)]
+    pub query: Option<Query>,
 
     /// Where to search for.
-    #[structopt(env = "PWD", parse(try_from_str = parse_path))]
+    #[structopt(env = "PWD", parse(try_from_str = parse_path), required = false)]
     pub path: PathBuf,
 }
 
+impl Args {
+    /// Parse the arguments of the process, see [`Args::parse_from`].
+    pub fn parse() -> Args {
+        Args::parse_from(std::env::args_os())
+    }
+
+    /// Parse the given arguments, exiting with an error if they are not valid. A subcommand name
+    /// before `--` runs the subcommand, while the words after `--` are always the query and the
+    /// path, so `marks -- graph` searches for "graph".
+    pub fn parse_from<I>(args: I) -> Args
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString> + Clone,
+    {
+        let args = args.into_iter().map(Into::into).collect::<Vec<OsString>>();
+        let mut app = Args::clap();
+        // clap suggests the subcommand instead of taking the query when the query after `--` is
+        // a subcommand name, unless subcommands are inferred
+        if args.iter().skip(1).any(|x| x == "--") {
+            app = app.setting(AppSettings::InferSubcommands);
+        }
+        Args::from_clap(&app.get_matches_from(args))
+    }
+
+    /// Should the results be explained, with either --explain or --explain-file?
+    pub fn is_explaining(&self) -> bool {
+        self.explain || self.explain_file.is_some()
+    }
+
+    /// Check the arguments that depend on each other, which can't be checked while parsing them.
+    pub fn validate(&self) -> Result<(), String> {
+        let priorities = self
+            .priority
+            .iter()
+            .chain(self.priority_lt.iter())
+            .chain(self.priority_gt.iter());
+        for priority in priorities {
+            if !self.priorities.contains(priority) {
+                return Err(format!(
+                    "priority `{}` is out of the range `{} {}`, see --priorities",
+                    priority, self.priorities.highest, self.priorities.lowest
+                ));
+            }
+        }
+
+        if self.sort == SortKey::None && self.rank == Rank::Bm25 {
+            return Err(
+                "--rank bm25 needs every result before scoring them, it can't be used with \
+                 --sort none"
+                    .to_string(),
+            );
+        }
+
+        if let Err(err) = self.overrides() {
+            return Err(format!("invalid glob: {}", err));
+        }
+
+        Ok(())
+    }
+
+    /// The globs of --glob and --iglob, which include or exclude files by their paths.
+    pub fn overrides(&self) -> Result<Override, ignore::Error> {
+        let mut builder = OverrideBuilder::new(&self.path);
+        for glob in &self.glob {
+            builder.add(glob)?;
+        }
+        builder.case_insensitive(true)?;
+        for glob in &self.iglob {
+            builder.add(glob)?;
+        }
+        builder.build()
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// List every location that links to the given target.
+    ///
+    /// The target may be a file (x.org), a header in a file (x.org::*Heading, x.md#anchor),
+    /// a header in any file (*Heading) or an ID (id:...).
+    Backlinks {
+        /// File, header or ID to find the links to.
+        target: String,
+    },
+
+    /// Report broken links and dangling footnotes.
+    ///
+    /// Links to missing files, missing headers or anchors, unknown IDs and footnotes without
+    /// definitions are reported as `file:line: message`. Exits with a non-zero code if any
+    /// problem is found.
+    CheckLinks,
+
+    /// Print the link graph between files and headers.
+    ///
+    /// Every file is a node. Headers become nodes only if they link to somewhere or they are
+    /// linked from somewhere.
+    Graph {
+        /// Output format, dot or json.
+        #[structopt(long, default_value = "dot", parse(try_from_str = parse_graph_format))]
+        format: GraphFormat,
+    },
+
+    /// List the files that are not linked from any other file.
+    Orphans,
+
+    /// List the dedicated targets, <<target>>, and radio targets, <<<target>>>, of org files.
+    Targets,
+
+    /// Manage the search index.
+    ///
+    /// The index keeps the parsed headers and the words of every file under the cache directory.
+    /// Searches use it automatically while it's fresh, meaning that no file has changed since it
+    /// was built or updated, and walk the tree otherwise.
+    Index {
+        #[structopt(subcommand)]
+        action: IndexAction,
+    },
+
+    /// Export tables.
+    ///
+    /// Exports the tables with the given name (given by #+NAME:), or if there is no such table,
+    /// the tables that have a row matching the given query. Queries may contain col:Name=value
+    /// terms to match rows by their columns.
+    Table {
+        /// Output format, csv or tsv.
+        #[structopt(long, default_value = "csv", parse(try_from_str = parse_table_format))]
+        export: TableFormat,
+
+        /// Name of the table or a query.
+        #[structopt(parse(try_from_str = parse_boxed_query))]
+        table: Box<Query>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum IndexAction {
+    /// Index every file from scratch.
+    Build,
+    /// Index the files that have changed since the last update, by their modification time, size
+    /// and contents.
+    Update,
+    /// Print how many files are indexed and whether the index is fresh.
+    Status,
+}
+
+#[derive(Debug)]
+pub enum TableFormat {
+    Csv,
+    Tsv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchUnit {
+    Line,
+    Paragraph,
+    Section,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rank {
+    Fuzzy,
+    Bm25,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    None,
+    Score,
+    Path,
+    Line,
+    Mtime,
+    Scheduled,
+    Deadline,
+    Priority,
+    Todo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatcherKind {
+    Skim,
+    Fzf,
+    Substring,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseMode {
+    Smart,
+    Ignore,
+    Respect,
+}
+
+#[derive(Debug)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
 fn parse_props<'a>(s: &'a str) -> Result<(String, String), String> {
     let pos = s
         .find('=')
@@ -125,10 +478,114 @@ fn parse_props<'a>(s: &'a str) -> Result<(String, String), String> {
     Ok((s[..pos].to_string(), s[pos + 1..].to_string()))
 }
 
+fn parse_boost(s: &str) -> Result<(String, f64), String> {
+    let (name, weight) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid NAME=weight: no `=` found in `{}`", s))?;
+    if !boost::NAMES.contains(&name) {
+        return Err(format!(
+            "unknown boost `{}`, expected one of {}",
+            name,
+            boost::NAMES.join(", ")
+        ));
+    }
+    let weight = weight
+        .parse()
+        .map_err(|_| format!("invalid weight `{}` for the {} boost", weight, name))?;
+    Ok((name.to_string(), weight))
+}
+
+fn parse_type(s: &str) -> Result<String, String> {
+    if formats::TYPE_NAMES.contains(&s) {
+        Ok(s.to_string())
+    } else {
+        Err(format!("unknown type `{}`, see --type-list", s))
+    }
+}
+
+fn parse_unit(s: &str) -> Result<SearchUnit, String> {
+    match s {
+        "line" => Ok(SearchUnit::Line),
+        "paragraph" => Ok(SearchUnit::Paragraph),
+        "section" => Ok(SearchUnit::Section),
+        x => Err(format!("unknown unit `{}`, expected line, paragraph or section", x)),
+    }
+}
+
+fn parse_rank(s: &str) -> Result<Rank, String> {
+    match s {
+        "fuzzy" => Ok(Rank::Fuzzy),
+        "bm25" => Ok(Rank::Bm25),
+        x => Err(format!("unknown ranking `{}`, expected fuzzy or bm25", x)),
+    }
+}
+
+fn parse_sort(s: &str) -> Result<SortKey, String> {
+    match s {
+        "none" => Ok(SortKey::None),
+        "score" => Ok(SortKey::Score),
+        "path" => Ok(SortKey::Path),
+        "line" => Ok(SortKey::Line),
+        "mtime" => Ok(SortKey::Mtime),
+        "scheduled" => Ok(SortKey::Scheduled),
+        "deadline" => Ok(SortKey::Deadline),
+        "priority" => Ok(SortKey::Priority),
+        "todo" => Ok(SortKey::Todo),
+        x => Err(format!(
+            "unknown sort order `{}`, expected none, score, path, line, mtime, scheduled, deadline, \
+             priority or todo",
+            x
+        )),
+    }
+}
+
+fn parse_matcher(s: &str) -> Result<MatcherKind, String> {
+    match s {
+        "skim" => Ok(MatcherKind::Skim),
+        "fzf" => Ok(MatcherKind::Fzf),
+        "substring" => Ok(MatcherKind::Substring),
+        "word" => Ok(MatcherKind::Word),
+        x => Err(format!(
+            "unknown matcher `{}`, expected skim, fzf, substring or word",
+            x
+        )),
+    }
+}
+
+fn parse_case(s: &str) -> Result<CaseMode, String> {
+    match s {
+        "smart" => Ok(CaseMode::Smart),
+        "ignore" => Ok(CaseMode::Ignore),
+        "respect" => Ok(CaseMode::Respect),
+        x => Err(format!("unknown case mode `{}`, expected smart, ignore or respect", x)),
+    }
+}
+
+fn parse_graph_format(s: &str) -> Result<GraphFormat, String> {
+    match s {
+        "dot" => Ok(GraphFormat::Dot),
+        "json" => Ok(GraphFormat::Json),
+        x => Err(format!("unknown graph format `{}`, expected dot or json", x)),
+    }
+}
+
+fn parse_table_format(s: &str) -> Result<TableFormat, String> {
+    match s {
+        "csv" => Ok(TableFormat::Csv),
+        "tsv" => Ok(TableFormat::Tsv),
+        x => Err(format!("unknown table format `{}`, expected csv or tsv", x)),
+    }
+}
+
 fn parse_query<'a>(s: &'a str) -> Result<Query, impl Error + 'a> {
     Query::new(s)
 }
 
+/// Queries of the subcommands are boxed, so that they don't make every [`Command`] as large.
+fn parse_boxed_query<'a>(s: &'a str) -> Result<Box<Query>, impl Error + 'a> {
+    parse_query(s).map(Box::new)
+}
+
 fn parse_path<'a>(s: &'a str) -> Result<PathBuf, impl Error + 'a> {
     PathBuf::from(s).canonicalize()
 }
@@ -142,7 +599,7 @@ fn parse_todos<'a>(s: &'a str) -> Result<OrgTodo, String> {
 }
 
 fn parse_priority<'a>(s: &'a str) -> Result<OrgPriority, String> {
-    Ok(OrgPriority(s.into()))
+    Ok(OrgPriority(s.to_uppercase()))
 }
 
 fn parse_org_date_time<'a>(s: &'a str, date_plan: OrgDatePlan) -> Result<OrgDateTime, impl Error + 'a> {
@@ -157,3 +614,29 @@ fn parse_org_date_time<'a>(s: &'a str, date_plan: OrgDatePlan) -> Result<OrgDate
 fn parse_org_scheduled<'a>(s: &'a str) -> Result<OrgDateTime, impl Error + 'a> {
     parse_org_date_time(s, OrgDatePlan::Scheduled)
 }
+
+#[test]
+fn test_subcommands() {
+    let args = Args::parse_from(["marks", "graph"]);
+    assert!(matches!(args.cmd, Some(Command::Graph { .. })));
+    assert!(args.query.is_none());
+
+    let args = Args::parse_from(["marks", "--no-color", "--", "graph", "."]);
+    assert!(args.cmd.is_none());
+    assert_eq!(args.query.map(|x| x.full), Some("graph".to_string()));
+
+    let args = Args::parse_from(["marks", "--", "-badword", "."]);
+    assert_eq!(args.query.map(|x| x.nones), Some(vec!["badword".to_string()]));
+}
+
+#[test]
+fn test_validate_priorities() {
+    let args = Args::parse_from(["marks", "x", "--priority-lt", "B"]);
+    assert!(args.validate().is_ok());
+
+    let args = Args::parse_from(["marks", "x", "--priority-lt=2"]);
+    assert!(args.validate().is_err());
+
+    let args = Args::parse_from(["marks", "x", "--priority-lt=2", "--priorities", "1 10 5"]);
+    assert!(args.validate().is_ok());
+}
@@ -0,0 +1,161 @@
+//! Boosts of the results, by where the query matched and what the section of the result is about,
+//! see `--boost`. Every boost turns a signal between 0.0 and 1.0 into a bonus, and the score of
+//! the result is multiplied by one plus the sum of the bonuses.
+
+use chrono::{DateTime, Utc};
+use combine::Parser;
+use std::collections::HashMap;
+
+use crate::args::Args;
+use crate::parsers;
+
+/// Names of the boosts, as accepted by `--boost`.
+pub const NAMES: [&str; 5] = ["header", "depth", "recency", "priority", "deadline"];
+
+/// Number of days after which the recency signal halves.
+const RECENCY_HALF_LIFE: f64 = 30.0;
+
+/// Number of days from the deadline after which the deadline signal halves.
+const DEADLINE_HALF_LIFE: f64 = 7.0;
+
+/// How much each boost counts. Boosts with a weight of 0 are off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weights {
+    /// Matches in the header of the result's section.
+    pub header: f64,
+    /// Sections under shallower headers.
+    pub depth: f64,
+    /// Recently created sections, by their CREATED property or the modification time of the file.
+    pub recency: f64,
+    /// Sections with a higher priority.
+    pub priority: f64,
+    /// Sections whose deadline is close, either upcoming or overdue.
+    pub deadline: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            header: 1.0,
+            depth: 0.5,
+            recency: 0.0,
+            priority: 0.0,
+            deadline: 0.0,
+        }
+    }
+}
+
+impl Weights {
+    /// The default weights, overridden by the ones given with `--boost`.
+    pub fn from_args(args: &Args) -> Weights {
+        let mut weights = Weights::default();
+        for (name, weight) in &args.boost {
+            match name.as_str() {
+                "header" => weights.header = *weight,
+                "depth" => weights.depth = *weight,
+                "recency" => weights.recency = *weight,
+                "priority" => weights.priority = *weight,
+                "deadline" => weights.deadline = *weight,
+                _ => {}
+            }
+        }
+        weights
+    }
+}
+
+/// Signals of a result that the boosts are computed from, each between 0.0 and 1.0. Signals that
+/// don't apply to the result, like the priority of a section without a priority, are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Signals {
+    /// Fraction of the query terms that match the header of the section.
+    pub header: f64,
+    /// 1.0 for top level sections, halves with every level.
+    pub depth: f64,
+    pub recency: Option<f64>,
+    pub priority: Option<f64>,
+    pub deadline: Option<f64>,
+}
+
+impl Signals {
+    /// Bonus of each boost that is on and applies to the result, by their names.
+    pub fn bonuses(&self, weights: &Weights) -> Vec<(&'static str, f64)> {
+        let signals = [
+            (weights.header, Some(self.header)),
+            (weights.depth, Some(self.depth)),
+            (weights.recency, self.recency),
+            (weights.priority, self.priority),
+            (weights.deadline, self.deadline),
+        ];
+
+        NAMES
+            .iter()
+            .zip(signals)
+            .filter(|(_, (weight, _))| *weight != 0.0)
+            .filter_map(|(name, (weight, signal))| Some((*name, weight * signal?)))
+            .collect()
+    }
+
+    /// What the score of the result is multiplied with.
+    pub fn multiplier(&self, weights: &Weights) -> f64 {
+        1.0 + self.bonuses(weights).iter().map(|(_, x)| x).sum::<f64>()
+    }
+}
+
+/// Signal of a section at the given depth, 0 being the text before the first header.
+pub fn depth(depth: usize) -> f64 {
+    0.5f64.powi(depth.saturating_sub(1) as i32)
+}
+
+/// Signal of something that was created at the given time.
+pub fn recency(time: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let days = (now - time).num_seconds().max(0) as f64 / 86400.0;
+    0.5f64.powf(days / RECENCY_HALF_LIFE)
+}
+
+/// Signal of a deadline at the given time.
+pub fn deadline(time: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let days = (time - now).num_seconds().abs() as f64 / 86400.0;
+    0.5f64.powf(days / DEADLINE_HALF_LIFE)
+}
+
+/// Time in the CREATED property, like `:CREATED: [2024-01-02 Tue 10:00]`.
+pub fn created(properties: &HashMap<String, String>) -> Option<DateTime<Utc>> {
+    let (_, value) = properties
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("CREATED"))?;
+    let value = value.trim().trim_start_matches(['[', '<']);
+    parsers::date_time_range()
+        .parse(value)
+        .ok()
+        .map(|((start, _), _)| start)
+}
+
+#[test]
+fn test_signals() {
+    let signals = Signals {
+        header: 1.0,
+        depth: depth(2),
+        recency: None,
+        priority: Some(0.5),
+        deadline: None,
+    };
+    let weights = Weights {
+        priority: 2.0,
+        ..Default::default()
+    };
+    assert_eq!(
+        signals.bonuses(&weights),
+        vec![("header", 1.0), ("depth", 0.25), ("priority", 1.0)]
+    );
+    assert_eq!(signals.multiplier(&weights), 3.25);
+
+    let now = Utc::now();
+    assert_eq!(recency(now, now), 1.0);
+    assert_eq!(deadline(now + chrono::Duration::days(7), now), 0.5);
+
+    let properties = HashMap::from([("Created".to_string(), "[2024-01-02 Tue 10:00]".to_string())]);
+    assert_eq!(
+        created(&properties).map(|x| x.to_rfc3339()),
+        Some("2024-01-02T10:00:00+00:00".to_string())
+    );
+}
@@ -0,0 +1,39 @@
+use itertools::Itertools;
+use std::io;
+
+use crate::commands::build_link_index;
+use crate::marks::Marks;
+use crate::result::SearchResult;
+
+/// Print every location that links to `target`.
+pub fn run(app: &Marks, target: &str) -> Result<(), io::Error> {
+    let index = build_link_index(app);
+    let targets = index.resolve_target(&std::env::current_dir()?, target);
+
+    if targets.is_empty() || targets.iter().any(|x| x.is_broken()) {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("could not find the target `{}`", target),
+        ));
+    }
+
+    let backlinks = index.backlinks(&targets);
+    // A line may contain multiple links to the same target, show it only once
+    let backlinks = backlinks
+        .iter()
+        .map(|(doc_idx, link_idx)| {
+            (
+                &index.documents[*doc_idx],
+                &index.documents[*doc_idx].links[*link_idx],
+            )
+        })
+        .unique_by(|(doc, link)| (&doc.path, link.line));
+
+    for (doc, link) in backlinks {
+        let headers = doc.hierarchy(link.line);
+        SearchResult::at(app.args, &doc.path, link.line + 1, headers, link.content.clone())
+            .print();
+    }
+
+    Ok(())
+}
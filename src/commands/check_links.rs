@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+use std::io;
+
+use crate::commands::build_link_index;
+use crate::links::Resolution;
+use crate::marks::Marks;
+use crate::org::link::LinkSearch;
+
+/// Print a `file:line: message` diagnostic for every broken link and dangling footnote.
+/// Exits with a non-zero code if anything is found.
+pub fn run(app: &Marks) -> Result<(), io::Error> {
+    let index = build_link_index(app);
+    let mut problems = 0;
+
+    for (doc_idx, doc) in index.documents.iter().enumerate() {
+        let path = doc.path.to_string_lossy();
+        let mut diagnostics = vec![];
+
+        for link in doc.links.iter() {
+            let message = match index.resolve(doc_idx, link) {
+                Resolution::MissingFile(file) => {
+                    format!("link to missing file `{}`", file.to_string_lossy())
+                }
+                Resolution::MissingSearch(target_idx, search) => {
+                    let search = match search {
+                        LinkSearch::Heading(x) => format!("heading `*{}`", x),
+                        LinkSearch::Anchor(x) => format!("anchor `#{}`", x),
+                        LinkSearch::Line(x) => format!("line `{}`", x),
+                        LinkSearch::Text(x) => format!("target `{}`", x),
+                    };
+                    format!(
+                        "link to missing {} in `{}`",
+                        search,
+                        index.documents[target_idx].path.to_string_lossy()
+                    )
+                }
+                Resolution::UnknownId(id) => format!("link to unknown id `{}`", id),
+                Resolution::UnknownNote(name) => format!("link to unknown note `{}`", name),
+                _ => continue,
+            };
+            diagnostics.push((link.line, message));
+        }
+
+        let definitions = doc
+            .footnotes
+            .iter()
+            .filter(|x| x.definition.is_some())
+            .map(|x| &x.name)
+            .collect::<HashSet<_>>();
+        let references = doc
+            .footnotes
+            .iter()
+            .filter(|x| x.is_reference)
+            .map(|x| &x.name)
+            .collect::<HashSet<_>>();
+
+        for footnote in doc.footnotes.iter() {
+            if footnote.is_reference && !definitions.contains(&footnote.name) {
+                diagnostics.push((
+                    footnote.line,
+                    format!("reference to undefined footnote `{}`", footnote.name),
+                ));
+            } else if !footnote.is_reference && !references.contains(&footnote.name) {
+                diagnostics.push((
+                    footnote.line,
+                    format!("footnote `{}` is never referenced", footnote.name),
+                ));
+            }
+        }
+
+        diagnostics.sort_by_key(|(line, _)| *line);
+        for (line, message) in diagnostics {
+            problems += 1;
+            println!("{}:{}: {}", path, line + 1, message);
+        }
+    }
+
+    if problems > 0 {
+        eprintln!("{} problem(s) found", problems);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use serde_json::json;
+
+use crate::args::GraphFormat;
+use crate::commands::build_link_index;
+use crate::links::{LinkIndex, Resolution};
+use crate::marks::Marks;
+
+/// A file or a header in the link graph.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+struct Node {
+    /// Path of the file, relative to the searched path.
+    file: String,
+    /// Header text, if this is a header node.
+    heading: Option<String>,
+}
+
+impl Node {
+    fn new(app: &Marks, index: &LinkIndex, doc_idx: usize, header_idx: Option<usize>) -> Node {
+        let doc = &index.documents[doc_idx];
+        let file = doc
+            .path
+            .strip_prefix(&app.args.path)
+            .unwrap_or(&doc.path)
+            .to_string_lossy()
+            .to_string();
+
+        Node {
+            file,
+            heading: header_idx.map(|x| doc.headers[x].content.trim().to_string()),
+        }
+    }
+
+    /// Identifier of the node, in the same format that `marks backlinks` accepts.
+    fn id(&self) -> String {
+        match &self.heading {
+            Some(heading) => format!("{}::*{}", self.file, heading),
+            None => self.file.clone(),
+        }
+    }
+}
+
+/// Print the link graph between files and headers.
+pub fn run(app: &Marks, format: &GraphFormat) -> Result<(), io::Error> {
+    let index = build_link_index(app);
+
+    // Link edges along with how many times the source links to the target
+    let mut edges: BTreeMap<(Node, Node), usize> = BTreeMap::new();
+    for (doc_idx, link, resolution) in index.resolved_links() {
+        let target = match resolution {
+            Resolution::File(x) | Resolution::Line(x, _) => Node::new(app, &index, x, None),
+            Resolution::Header(x, header_idx) => Node::new(app, &index, x, Some(header_idx)),
+            _ => continue,
+        };
+
+        let doc = &index.documents[doc_idx];
+        let source_header = doc.headers.iter().rposition(|x| x.line <= link.line);
+        let source = Node::new(app, &index, doc_idx, source_header);
+
+        *edges.entry((source, target)).or_default() += 1;
+    }
+
+    // Every file is a node, headers are only included if they take part in a link
+    let mut nodes = (0..index.documents.len())
+        .map(|x| Node::new(app, &index, x, None))
+        .collect::<Vec<_>>();
+    nodes.extend(edges.keys().flat_map(|(x, y)| [x.clone(), y.clone()]));
+    nodes.sort();
+    nodes.dedup();
+
+    match format {
+        GraphFormat::Dot => print_dot(&nodes, &edges),
+        GraphFormat::Json => print_json(&nodes, &edges),
+    }
+
+    Ok(())
+}
+
+fn print_dot(nodes: &[Node], edges: &BTreeMap<(Node, Node), usize>) {
+    let quote = |x: &str| format!("\"{}\"", x.replace('\\', "\\\\").replace('"', "\\\""));
+
+    println!("digraph marks {{");
+    for node in nodes {
+        match &node.heading {
+            Some(heading) => {
+                println!(
+                    "  {} [label={}, shape=box];",
+                    quote(&node.id()),
+                    quote(heading)
+                );
+                // Connect headers to their files so that the graph stays readable
+                println!(
+                    "  {} -> {} [style=dotted, arrowhead=none];",
+                    quote(&node.file),
+                    quote(&node.id())
+                );
+            }
+            None => println!("  {} [label={}];", quote(&node.id()), quote(&node.file)),
+        }
+    }
+
+    for ((source, target), count) in edges {
+        println!(
+            "  {} -> {} [weight={}];",
+            quote(&source.id()),
+            quote(&target.id()),
+            count
+        );
+    }
+    println!("}}");
+}
+
+fn print_json(nodes: &[Node], edges: &BTreeMap<(Node, Node), usize>) {
+    let nodes = nodes
+        .iter()
+        .map(|x| {
+            json!({
+                "id": x.id(),
+                "type": if x.heading.is_some() { "heading" } else { "file" },
+                "file": x.file,
+                "heading": x.heading,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let edges = edges
+        .iter()
+        .map(|((source, target), count)| {
+            json!({
+                "source": source.id(),
+                "target": target.id(),
+                "count": count,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    println!("{}", json!({ "nodes": nodes, "edges": edges }));
+}
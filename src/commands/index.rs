@@ -0,0 +1,43 @@
+use std::io;
+
+use crate::args::IndexAction;
+use crate::index::{self, SearchIndex};
+use crate::marks::Marks;
+
+/// Build or update the index of the searched directory, or print its status.
+pub fn run(app: &Marks, action: &IndexAction) -> Result<(), io::Error> {
+    match action {
+        IndexAction::Build => {
+            let index = SearchIndex::build(app);
+            index.save(app.args)?;
+            println!("indexed {} files", index.files.len());
+        }
+        IndexAction::Update => {
+            let (index, stats) = SearchIndex::update(app, SearchIndex::load(app.args));
+            index.save(app.args)?;
+            println!(
+                "{} added, {} updated, {} removed, {} unchanged",
+                stats.added, stats.updated, stats.removed, stats.unchanged
+            );
+        }
+        IndexAction::Status => {
+            let path = index::index_path(app.args);
+            let Some(index) = SearchIndex::load(app.args) else {
+                println!(
+                    "no index for {}, see `marks index build`",
+                    app.args.path.display()
+                );
+                return Ok(());
+            };
+
+            let headers = index.files.iter().map(|x| x.headers.len()).sum::<usize>();
+            println!("index: {}", path.display());
+            println!("files: {}", index.files.len());
+            println!("headers: {}", headers);
+            println!("terms: {}", index.terms.len());
+            println!("fresh: {}", if index.is_fresh(app) { "yes" } else { "no" });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! Subcommands other than the search itself.
+
+pub mod backlinks;
+pub mod check_links;
+pub mod graph;
+pub mod index;
+pub mod orphans;
+pub mod table;
+pub mod targets;
+
+use rayon::prelude::*;
+
+use crate::links::LinkIndex;
+use crate::marks::Marks;
+
+/// Parse every file in the tree and index them for link resolution.
+pub fn build_link_index<'a>(app: &'a Marks<'a>) -> LinkIndex<'a> {
+    let documents = app
+        .find_files()
+        .collect::<Vec<_>>()
+        .par_iter()
+        .filter_map(|f| app.parse_document(f.path()))
+        .collect::<Vec<_>>();
+
+    LinkIndex::new(documents)
+}
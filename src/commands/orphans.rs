@@ -0,0 +1,25 @@
+use std::collections::HashSet;
+use std::io;
+
+use crate::commands::build_link_index;
+use crate::marks::Marks;
+
+/// Print the files that are not linked from any other file.
+pub fn run(app: &Marks) -> Result<(), io::Error> {
+    let index = build_link_index(app);
+
+    let linked = index
+        .resolved_links()
+        .filter_map(|(doc_idx, _, resolution)| {
+            resolution.document().filter(|target| *target != doc_idx)
+        })
+        .collect::<HashSet<_>>();
+
+    for (doc_idx, doc) in index.documents.iter().enumerate() {
+        if !linked.contains(&doc_idx) {
+            println!("{}", doc.path.to_string_lossy());
+        }
+    }
+
+    Ok(())
+}
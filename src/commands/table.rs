@@ -0,0 +1,73 @@
+use std::io;
+
+use rayon::prelude::*;
+
+use crate::args::TableFormat;
+use crate::marks::Marks;
+use crate::query::Query;
+
+/// Export the tables with the given name, or the tables that have a row matching the given query.
+pub fn run(app: &Marks, format: &TableFormat, query: &Query) -> Result<(), io::Error> {
+    let mut documents = app
+        .find_files()
+        .collect::<Vec<_>>()
+        .par_iter()
+        .filter_map(|f| app.parse_document(f.path()))
+        .collect::<Vec<_>>();
+    documents.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let named = documents
+        .iter()
+        .flat_map(|doc| doc.tables.iter())
+        .filter(|x| x.name.as_deref() == Some(query.full.trim()))
+        .collect::<Vec<_>>();
+
+    let tables = if !named.is_empty() {
+        named
+    } else {
+        documents
+            .iter()
+            .flat_map(|doc| doc.tables.iter().map(move |table| (doc, table)))
+            .filter(|(doc, table)| {
+                let headers = doc
+                    .hierarchy(table.line)
+                    .iter()
+                    .map(|x| x.content.to_owned())
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+                table.rows.iter().any(|row| {
+                    let columns_match = query.cols.is_empty()
+                        || table
+                            .header
+                            .as_ref()
+                            .is_some_and(|header| app.matches_columns(query, header, &row.cells));
+                    let full = format!("{}{}", headers, row.cells.join(" | "));
+                    columns_match && app.match_query(query, &full).is_some()
+                })
+            })
+            .map(|(_, table)| table)
+            .collect::<Vec<_>>()
+    };
+
+    if tables.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no table found for `{}`", query.full),
+        ));
+    }
+
+    let delimiter = match format {
+        TableFormat::Csv => ',',
+        TableFormat::Tsv => '\t',
+    };
+
+    // Separate the tables with an empty line if there are many of them
+    let output = tables
+        .iter()
+        .map(|x| x.to_csv(delimiter))
+        .collect::<Vec<_>>()
+        .join("\n");
+    print!("{}", output);
+
+    Ok(())
+}
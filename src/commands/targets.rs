@@ -0,0 +1,19 @@
+use std::io;
+
+use crate::commands::build_link_index;
+use crate::marks::Marks;
+use crate::result::SearchResult;
+
+/// Print every dedicated and radio target, so that they can be used as jump points.
+pub fn run(app: &Marks) -> Result<(), io::Error> {
+    let index = build_link_index(app);
+
+    for doc in &index.documents {
+        for target in &doc.targets {
+            let headers = doc.hierarchy(target.line);
+            SearchResult::at(app.args, &doc.path, target.line + 1, headers, target.raw()).print();
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! Explanations of why the results matched and why the sections of a file were skipped, see
+//! `--explain` and `--explain-file`.
+
+use regex::Regex;
+
+use crate::args::Args;
+use crate::query::Query;
+
+/// How a term of the query matched the text of a result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermMatch {
+    /// fuzzy, must, regex or none.
+    pub kind: &'static str,
+    pub term: String,
+    /// Score of the fuzzy terms.
+    pub score: Option<i64>,
+    /// Spans of the text that the term matched, as character ranges. Empty for the none terms,
+    /// which can't appear in the text.
+    pub spans: Vec<(usize, usize)>,
+}
+
+/// Why a result matched and how it was scored, apart from the boosts. Only collected with
+/// --explain.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Explanation {
+    /// The text that the query was matched against, the headers and the lines of the result.
+    pub text: String,
+    pub terms: Vec<TermMatch>,
+    /// Filters other than the query terms that the result passed, like --tagged.
+    pub filters: Vec<String>,
+    /// BM25 score of each query term. Only with --rank bm25.
+    pub bm25: Vec<(String, f64)>,
+}
+
+impl Explanation {
+    /// Lines that describe the explanation, each with its label.
+    pub fn lines(&self) -> Vec<(&'static str, String)> {
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let mut lines = vec![("text", self.text.clone())];
+
+        for term in &self.terms {
+            let mut line = format!("{} {}", term.kind, term.term);
+            if let Some(score) = term.score {
+                line.push_str(&format!(" ({})", score));
+            }
+
+            if term.kind == "none" {
+                line.push_str(" (absent)");
+            } else if term.spans.is_empty() {
+                line.push_str(" (no match)");
+            } else {
+                let spans = term
+                    .spans
+                    .iter()
+                    .map(|(start, end)| {
+                        let text = chars[*start..*end].iter().collect::<String>();
+                        format!("{}..{} {:?}", start, end, text)
+                    })
+                    .collect::<Vec<_>>();
+                line.push_str(&format!(" at {}", spans.join(", ")));
+            }
+            lines.push(("match", line));
+        }
+
+        if !self.bm25.is_empty() {
+            let scores = self
+                .bm25
+                .iter()
+                .map(|(term, score)| format!("{} {:.3}", term, score))
+                .collect::<Vec<_>>();
+            lines.push(("bm25", scores.join(", ")));
+        }
+
+        if !self.filters.is_empty() {
+            lines.push(("filters", self.filters.join(", ")));
+        }
+
+        lines
+    }
+}
+
+/// Why a section of the file given with --explain-file was skipped, if it was.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionTrace {
+    pub line: usize,
+    /// The header of the section, `None` for the text before the first header.
+    pub header: Option<String>,
+    /// Empty if the section was searched.
+    pub reasons: Vec<String>,
+}
+
+/// Describe the filters that are given in the arguments and the query, other than the terms
+/// that are matched against the text.
+pub fn filters(args: &Args, query: &Query) -> Vec<String> {
+    let with_value = |name: &str, key: &str, value: &Option<String>| match value {
+        Some(value) => format!("{}:{}={}", name, key, value),
+        None => format!("{}:{}", name, key),
+    };
+
+    let mut filters = vec![];
+    filters.extend(query.links.iter().map(|x| format!("link:{}", x)));
+    filters.extend(
+        query
+            .cols
+            .iter()
+            .map(|(key, value)| with_value("col", key, value)),
+    );
+    filters.extend(query.drawers.iter().map(|x| format!("drawer:{}", x)));
+    filters.extend(query.langs.iter().map(|x| format!("lang:{}", x)));
+    filters.extend(query.tangles.iter().map(|x| format!("tangle:{}", x)));
+    filters.extend(query.blocks.iter().map(|x| format!("block:{}", x)));
+    filters.extend(
+        query
+            .block_args
+            .iter()
+            .map(|(key, value)| with_value("arg", key, value)),
+    );
+    filters.extend(args.tagged.iter().map(|x| format!("tagged {}", x)));
+    filters.extend(
+        args.prop
+            .iter()
+            .map(|(key, value)| format!("prop {}={}", key, value)),
+    );
+    if !args.todo.is_empty() {
+        let todos = args.todo.iter().map(|x| x.keyword()).collect::<Vec<_>>();
+        filters.push(format!("todo {}", todos.join("|")));
+    }
+    if !args.priority.is_empty() {
+        let priorities = args
+            .priority
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        filters.push(format!("priority {}", priorities.join("|")));
+    }
+    filters.extend(args.priority_lt.iter().map(|x| format!("priority < {}", x)));
+    filters.extend(args.priority_gt.iter().map(|x| format!("priority > {}", x)));
+    filters.extend(
+        args.scheduled_at
+            .iter()
+            .map(|x| format!("scheduled at {}", x.date_start.format("%Y-%m-%d"))),
+    );
+    filters
+}
+
+/// Group the indices of the matched characters into ranges of consecutive characters.
+pub fn spans(indices: &[usize]) -> Vec<(usize, usize)> {
+    let mut spans: Vec<(usize, usize)> = vec![];
+    for &idx in indices {
+        match spans.last_mut() {
+            Some((_, end)) if *end == idx => *end += 1,
+            _ => spans.push((idx, idx + 1)),
+        }
+    }
+    spans
+}
+
+/// Spans of the text that the regex matches, as character ranges.
+pub fn regex_spans(regex: &Regex, text: &str) -> Vec<(usize, usize)> {
+    regex
+        .find_iter(text)
+        .map(|x| (char_offset(text, x.start()), char_offset(text, x.end())))
+        .collect()
+}
+
+/// Spans of the text where the term appears, as character ranges.
+pub fn literal_spans(term: &str, text: &str) -> Vec<(usize, usize)> {
+    text.match_indices(term)
+        .map(|(start, x)| (char_offset(text, start), char_offset(text, start + x.len())))
+        .collect()
+}
+
+fn char_offset(text: &str, byte: usize) -> usize {
+    text[..byte].chars().count()
+}
+
+#[test]
+fn test_spans() {
+    assert_eq!(spans(&[0, 1, 2, 5, 7, 8]), vec![(0, 3), (5, 6), (7, 9)]);
+    assert_eq!(literal_spans("ab", "äab ab"), vec![(1, 3), (4, 6)]);
+
+    let explanation = Explanation {
+        text: "Rust notes".into(),
+        terms: vec![TermMatch {
+            kind: "fuzzy",
+            term: "rn".into(),
+            score: Some(40),
+            spans: vec![(0, 1), (5, 6)],
+        }],
+        ..Default::default()
+    };
+    assert_eq!(
+        explanation.lines()[1],
+        (
+            "match",
+            r#"fuzzy rn (40) at 0..1 "R", 5..6 "n""#.to_string()
+        )
+    );
+}
@@ -0,0 +1,61 @@
+use crate::formats::{parse_marked_header, DocumentFormat, HeaderLine};
+use crate::marks::DocType;
+
+/// AsciiDoc files. Headers start with `=`, `= Title` being the document title. Blocks are
+/// delimited by lines like `----` or `....`.
+pub struct AsciiDoc {
+    extensions: Vec<String>,
+}
+
+impl Default for AsciiDoc {
+    fn default() -> AsciiDoc {
+        AsciiDoc {
+            extensions: vec!["adoc".into(), "asciidoc".into(), "asc".into()],
+        }
+    }
+}
+
+impl DocumentFormat for AsciiDoc {
+    fn name(&self) -> &str {
+        "asciidoc"
+    }
+
+    fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    fn doc_type(&self) -> DocType {
+        DocType::Markdown
+    }
+
+    fn parse_header(&self, line: &str, _next: Option<&str>) -> Option<HeaderLine> {
+        parse_marked_header(line, '=')
+    }
+
+    fn block_start(&self, line: &str) -> Option<String> {
+        let line = line.trim_end();
+        let delimiter = line.chars().next()?;
+        let is_delimiter = matches!(delimiter, '-' | '.' | '=' | '*' | '_' | '+' | '/')
+            && line.len() >= 4
+            && line.chars().all(|x| x == delimiter);
+
+        is_delimiter.then(|| line.to_string())
+    }
+
+    fn is_block_end(&self, end: &str, line: &str) -> bool {
+        line.trim_end() == end
+    }
+}
+
+#[test]
+fn test_asciidoc_format() {
+    let adoc = AsciiDoc::default();
+    assert_eq!(
+        adoc.parse_header("== Section", None).map(|x| x.depth),
+        Some(2)
+    );
+    assert_eq!(adoc.parse_header("==== ", None).map(|x| x.depth), Some(4));
+    assert_eq!(adoc.parse_header("====", None), None);
+    assert_eq!(adoc.block_start("----"), Some("----".into()));
+    assert_eq!(adoc.block_start("--"), None);
+}
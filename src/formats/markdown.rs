@@ -0,0 +1,90 @@
+use crate::formats::{parse_marked_header, parse_org_tags, DocumentFormat, HeaderLine};
+use crate::marks::DocType;
+use crate::org::block::OrgBlock;
+
+/// Markdown files. Headers start with `#`, blocks are fenced with ``` or `~~~`.
+pub struct Markdown {
+    extensions: Vec<String>,
+}
+
+impl Markdown {
+    pub fn new(extensions: Vec<String>) -> Markdown {
+        Markdown { extensions }
+    }
+}
+
+impl DocumentFormat for Markdown {
+    fn name(&self) -> &str {
+        "md"
+    }
+
+    fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    fn doc_type(&self) -> DocType {
+        DocType::Markdown
+    }
+
+    fn parse_header(&self, line: &str, _next: Option<&str>) -> Option<HeaderLine> {
+        parse_marked_header(line, '#')
+    }
+
+    // Org style tags, planning lines and properties are also picked up from markdown headers
+    fn parse_tags(&self, text: &str) -> (Vec<String>, String) {
+        parse_org_tags(text)
+    }
+
+    fn has_planning(&self) -> bool {
+        true
+    }
+
+    fn has_drawers(&self) -> bool {
+        true
+    }
+
+    fn block_start(&self, line: &str) -> Option<String> {
+        let line = line.trim_start();
+        let fence = line.chars().next().filter(|x| *x == '`' || *x == '~')?;
+        let width = line.chars().take_while(|x| *x == fence).count();
+
+        (width >= 3).then(|| fence.to_string().repeat(width))
+    }
+
+    fn is_block_end(&self, end: &str, line: &str) -> bool {
+        let line = line.trim();
+        line.starts_with(end) && line.chars().all(|x| end.starts_with(x))
+    }
+
+    // The info string of a fence starts with the language, like ```python or ```{python}
+    fn parse_block(&self, line: &str) -> OrgBlock {
+        let info = line.trim().trim_start_matches(['`', '~']);
+        let lang = info
+            .split(|x: char| x.is_whitespace() || x == ',' || x == '}')
+            .next()
+            .map(|x| x.trim_start_matches('{'))
+            .filter(|x| !x.is_empty());
+
+        OrgBlock {
+            kind: "SRC".into(),
+            lang: lang.map(String::from),
+            ..Default::default()
+        }
+    }
+}
+
+#[test]
+fn test_markdown_format() {
+    let md = Markdown::new(vec!["md".into()]);
+    assert_eq!(
+        md.parse_header("### Header", None).map(|x| x.depth),
+        Some(3)
+    );
+    assert_eq!(md.parse_header("#hashtag", None), None);
+    assert_eq!(md.block_start("````python"), Some("````".into()));
+    assert!(!md.is_block_end("````", "```"));
+    assert!(md.is_block_end("```", "````"));
+    assert_eq!(md.parse_block("```python").lang.as_deref(), Some("python"));
+    assert_eq!(md.parse_block("```{r, echo=FALSE}").lang.as_deref(), Some("r"));
+    assert_eq!(md.parse_block("```").lang, None);
+}
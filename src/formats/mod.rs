@@ -0,0 +1,370 @@
+//! Document formats that can be searched. Each format knows how to recognize its files and how
+//! to parse the structure of a document, like headers and blocks. Inline syntax, like links and
+//! footnotes, is shared between the formats through [`DocType`].
+
+use std::path::{Path, PathBuf};
+
+use combine::Parser;
+
+use crate::args::Args;
+use crate::extensions::StartsWithIgnoreCase;
+use crate::marks::DocType;
+use crate::org::block::OrgBlock;
+use crate::org::header::{OrgPriority, OrgTodo};
+use crate::parsers;
+
+pub mod asciidoc;
+pub mod markdown;
+pub mod norg;
+pub mod notebook;
+pub mod org;
+pub mod plain;
+pub mod rst;
+
+/// Names of all formats, as accepted by `--type`.
+pub const TYPE_NAMES: [&str; 7] = ["org", "md", "asciidoc", "rst", "norg", "ipynb", "txt"];
+
+/// Formats that are searched when no `--type` is given.
+pub const DEFAULT_TYPES: [&str; 2] = ["org", "md"];
+
+/// A header line, before the format independent parts (like TODO states) are parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct HeaderLine {
+    pub depth: usize,
+    /// Text of the header without the markers that make it a header.
+    pub text: String,
+    /// Is the header underlined, meaning that the next line is a part of the header?
+    pub is_underlined: bool,
+}
+
+/// The text of a file that is searched.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SourceText {
+    pub text: String,
+    /// For files that are made of cells, like notebooks, the index of each cell along with the
+    /// line in `text` where the cell starts. Empty for other files.
+    pub cells: Vec<(usize, usize)>,
+    /// Files whose contents are included into `text`, with `#+INCLUDE:`.
+    pub includes: Vec<PathBuf>,
+    /// For every line in `text`, the index of the file in `includes` that the line comes from
+    /// (`None` for the file itself) and the line in that file. Empty if nothing is included.
+    pub origins: Vec<(Option<usize>, usize)>,
+}
+
+impl SourceText {
+    /// Find the cell index and the line in the cell for the given line in `text`. Files without
+    /// cells return the line as is.
+    pub fn locate(&self, line: usize) -> (Option<usize>, usize) {
+        match self.cells.iter().rev().find(|(_, start)| *start <= line) {
+            Some((cell, start)) => (Some(*cell), line - start),
+            None => (None, line),
+        }
+    }
+
+    /// Find the file and the line in it that the given line in `text` comes from. `None` means
+    /// the line is from the file itself.
+    pub fn origin(&self, line: usize) -> (Option<&Path>, usize) {
+        match self.origins.get(line) {
+            Some((file, line)) => (file.map(|x| self.includes[x].as_path()), *line),
+            None => (None, line),
+        }
+    }
+}
+
+pub trait DocumentFormat: Send + Sync {
+    /// Name of the format, used with `--type`.
+    fn name(&self) -> &str;
+
+    /// Extensions of the files in this format, without the dot.
+    fn extensions(&self) -> &[String];
+
+    /// Which syntax is used for the inline markup, links and footnotes.
+    fn doc_type(&self) -> DocType;
+
+    /// Parse the given line as a header. `next` is the line that comes after it.
+    fn parse_header(&self, line: &str, next: Option<&str>) -> Option<HeaderLine>;
+
+    /// Split the tags from the header text. Returns the tags and the rest of the text.
+    fn parse_tags(&self, text: &str) -> (Vec<String>, String) {
+        (vec![], text.trim().to_string())
+    }
+
+    /// Parse the TODO state and the priority at the start of the header text.
+    fn parse_todo(&self, text: &str) -> (Option<OrgTodo>, Option<OrgPriority>, String) {
+        parse_org_todo(text)
+    }
+
+    /// Parse the COMMENT keyword, which comes after the TODO state and the priority. Returns
+    /// whether the header is commented and the rest of the text.
+    fn parse_comment(&self, text: &str) -> (bool, String) {
+        (false, text.to_string())
+    }
+
+    /// Can files have settings, like `#+TODO:` or `#+SETUPFILE:`, and `#+INCLUDE:` other files?
+    fn has_file_settings(&self) -> bool {
+        false
+    }
+
+    /// Can headers be followed by SCHEDULED/DEADLINE lines?
+    fn has_planning(&self) -> bool {
+        false
+    }
+
+    /// Can sections have drawers, like `:PROPERTIES:` directly after the header or `:LOGBOOK:`
+    /// anywhere in the section?
+    fn has_drawers(&self) -> bool {
+        false
+    }
+
+    /// If the given line starts a block, like a code block, return the delimiter that ends it.
+    fn block_start(&self, _line: &str) -> Option<String> {
+        None
+    }
+
+    /// Does the given line end the block whose end delimiter is `end`?
+    fn is_block_end(&self, end: &str, line: &str) -> bool {
+        line.trim().starts_with_i(end)
+    }
+
+    /// Does the given line end any block before it, like a headline in org? A block that is not
+    /// ended before such a line is not a block.
+    fn ends_blocks(&self, _line: &str) -> bool {
+        false
+    }
+
+    /// Parse the metadata of the block that the given line starts, like its language. Only called
+    /// for the lines that `block_start` accepts.
+    fn parse_block(&self, _line: &str) -> OrgBlock {
+        OrgBlock::default()
+    }
+
+    /// If the line gives a name to the block after it, like `#+NAME: x`, return the name.
+    fn parse_block_name(&self, _line: &str) -> Option<String> {
+        None
+    }
+
+    /// Turn the contents of a file into the text that is searched. Most formats are searched as
+    /// is. Returns an error message if the file can't be read as this format.
+    fn extract(&self, text: String) -> Result<SourceText, String> {
+        Ok(SourceText {
+            text,
+            ..Default::default()
+        })
+    }
+
+    /// Does the file at the given path belong to this format?
+    fn matches(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|x| x.to_str())
+            .is_some_and(|x| self.extensions().iter().any(|y| x == y))
+    }
+}
+
+/// All supported formats.
+pub fn all(args: &Args) -> Vec<Box<dyn DocumentFormat>> {
+    vec![
+        Box::new(org::Org::new(args.org_extension.clone(), args.include_archived)),
+        Box::new(markdown::Markdown::new(args.md_extension.clone())),
+        Box::new(asciidoc::AsciiDoc::default()),
+        Box::new(rst::Rst::default()),
+        Box::new(norg::Norg::default()),
+        Box::new(notebook::Notebook::new(args.notebook_code)),
+        Box::new(plain::Plain::default()),
+    ]
+}
+
+/// Formats that are selected with `--type`, `--type-not`, `--no-org` and `--no-markdown`.
+pub fn enabled(args: &Args) -> Vec<Box<dyn DocumentFormat>> {
+    all(args)
+        .into_iter()
+        .filter(|format| {
+            let name = format.name();
+            let is_selected = if args.types.is_empty() {
+                DEFAULT_TYPES.contains(&name)
+            } else {
+                args.types.iter().any(|x| x == name)
+            };
+
+            let is_excluded = args.types_not.iter().any(|x| x == name)
+                || (args.no_org && name == "org")
+                || (args.no_markdown && name == "md");
+
+            is_selected && !is_excluded
+        })
+        .collect()
+}
+
+/// Parse org style tags, like `:tag1:tag2:`, from the end of the header text.
+pub fn parse_org_tags(text: &str) -> (Vec<String>, String) {
+    let rev_header = text.trim().chars().rev().collect::<String>();
+
+    if rev_header.starts_with(':') {
+        let result: Result<(Vec<String>, &str), _> = parsers::org_tags().parse(rev_header.as_str());
+        if let Ok((tags, rest)) = result {
+            // Tags are parsed from the end, put them back in order
+            return (
+                tags.into_iter().rev().collect(),
+                rest.chars().rev().collect(),
+            );
+        }
+    }
+
+    (vec![], text.trim().to_string())
+}
+
+/// Parse org style TODO keywords and priorities, like `TODO [#A]`, at the start of the header text.
+pub fn parse_org_todo(text: &str) -> (Option<OrgTodo>, Option<OrgPriority>, String) {
+    match parsers::org_todo().parse(text) {
+        Ok(((todo, priority), rest)) => (todo, priority, rest.to_string()),
+        Err(_) => (None, None, text.to_string()),
+    }
+}
+
+/// Count the leading `marker` characters of a header like `** Header`. The markers should be
+/// followed by a space.
+fn parse_marked_header(line: &str, marker: char) -> Option<HeaderLine> {
+    let depth = line.chars().take_while(|x| *x == marker).count();
+    let text = line[depth * marker.len_utf8()..].strip_prefix(' ')?;
+
+    (depth > 0).then(|| HeaderLine {
+        depth,
+        text: text.to_string(),
+        is_underlined: false,
+    })
+}
+
+/// Keeps track of whether the lines are inside of a block, like a code block, while going through
+/// a file line by line.
+#[derive(Debug, Default)]
+pub struct BlockTracker {
+    /// The delimiter that ends the current block.
+    end: Option<String>,
+    /// The block that the last pushed line belongs to.
+    current: Option<OrgBlock>,
+    /// Name for the next block, from a line like `#+NAME:` right before it.
+    name: Option<String>,
+    /// Did the last pushed line end the current block?
+    is_finished: bool,
+}
+
+impl BlockTracker {
+    /// Feed the next line, along with the lines after it. Returns whether the line is a part of a
+    /// block, including the lines that start and end the block. A line only starts a block if
+    /// the block is ended before a line that ends blocks, see [`DocumentFormat::ends_blocks`].
+    pub fn push<'l, I>(
+        &mut self,
+        format: &dyn DocumentFormat,
+        idx: usize,
+        line: &str,
+        rest: I,
+    ) -> bool
+    where
+        I: IntoIterator<Item = &'l str>,
+    {
+        if self.is_finished {
+            self.current = None;
+            self.is_finished = false;
+        }
+
+        match &self.end {
+            Some(end) => {
+                if format.is_block_end(end, line) {
+                    self.end = None;
+                    self.is_finished = true;
+                } else if let Some(block) = &mut self.current {
+                    block.content.push(line.to_string());
+                }
+                true
+            }
+            None => {
+                self.end = format.block_start(line).filter(|end| {
+                    let mut rest = rest.into_iter();
+                    let next = rest.find(|x| format.is_block_end(end, x) || format.ends_blocks(x));
+                    next.is_none_or(|x| !format.ends_blocks(x))
+                });
+                if self.end.is_some() {
+                    self.current = Some(OrgBlock {
+                        name: self.name.take(),
+                        line: idx,
+                        ..format.parse_block(line)
+                    });
+                } else {
+                    self.name = format.parse_block_name(line);
+                }
+                self.end.is_some()
+            }
+        }
+    }
+
+    /// The block that the last pushed line belongs to.
+    pub fn current(&self) -> Option<&OrgBlock> {
+        self.current.as_ref()
+    }
+
+    /// Did the last pushed line end the current block?
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+
+    /// The block that the last pushed line has ended, if there is any.
+    pub fn take_finished(&mut self) -> Option<OrgBlock> {
+        if self.is_finished {
+            self.is_finished = false;
+            self.current.take()
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_parse_org_tags() {
+    assert_eq!(
+        parse_org_tags("Header :a:b:"),
+        (vec!["a".into(), "b".into()], "Header".into())
+    );
+    assert_eq!(parse_org_tags("Header"), (vec![], "Header".into()));
+}
+
+#[test]
+fn test_block_tracker() {
+    let format = markdown::Markdown::new(vec!["md".into()]);
+    let mut tracker = BlockTracker::default();
+    let lines = ["text", "```sh", "# comment", "```", "# Header"];
+    assert_eq!(
+        lines.map(|x| tracker.push(&format, 0, x, [])),
+        [false, true, true, true, false]
+    );
+
+    // A headline ends an org block that is still open
+    let format = org::Org::new(vec!["org".into()], false);
+    let mut tracker = BlockTracker::default();
+    let lines = ["#+begin_quote", "quoted", "* Header", "#+end_quote"];
+    let in_block = lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| tracker.push(&format, idx, line, lines[idx + 1..].iter().copied()))
+        .collect::<Vec<_>>();
+    assert_eq!(in_block, [false, false, false, false]);
+}
+
+#[test]
+fn test_block_metadata() {
+    let format = org::Org::new(vec!["org".into()], false);
+    let mut tracker = BlockTracker::default();
+    let lines = [
+        "#+NAME: build",
+        "#+begin_src sh :dir /ssh:host:/srv",
+        "make",
+        "#+end_src",
+    ];
+    for (idx, line) in lines.iter().enumerate() {
+        tracker.push(&format, idx, line, lines[idx + 1..].iter().copied());
+    }
+
+    let block = tracker.take_finished().unwrap();
+    assert_eq!(block.name.as_deref(), Some("build"));
+    assert_eq!(block.lang.as_deref(), Some("sh"));
+    assert_eq!(block.arg("dir"), Some("/ssh:host:/srv"));
+    assert_eq!((block.line, block.content), (1, vec!["make".to_string()]));
+}
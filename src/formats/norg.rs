@@ -0,0 +1,106 @@
+use crate::formats::{parse_marked_header, DocumentFormat, HeaderLine};
+use crate::marks::DocType;
+use crate::org::block::OrgBlock;
+use crate::org::header::{OrgPriority, OrgTodo};
+
+/// Ranged tags that start blocks, like `@code lua`. Other lines that start with `@` or `|`, like
+/// "@alice said", are just text.
+const RANGED_TAGS: [&str; 10] = [
+    "@code",
+    "@document.meta",
+    "@embed",
+    "@image",
+    "@math",
+    "@table",
+    "|comment",
+    "|details",
+    "|example",
+    "|group",
+];
+
+/// Neorg files. Headers start with `*` like org, but TODO states are written like `( )` or `(x)`
+/// and there are no tags. Blocks are `@code ... @end` or `|example ... |end`.
+pub struct Norg {
+    extensions: Vec<String>,
+}
+
+impl Default for Norg {
+    fn default() -> Norg {
+        Norg {
+            extensions: vec!["norg".into()],
+        }
+    }
+}
+
+impl DocumentFormat for Norg {
+    fn name(&self) -> &str {
+        "norg"
+    }
+
+    fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    fn doc_type(&self) -> DocType {
+        DocType::OrgMode
+    }
+
+    fn parse_header(&self, line: &str, _next: Option<&str>) -> Option<HeaderLine> {
+        parse_marked_header(line.trim_start(), '*')
+    }
+
+    fn parse_todo(&self, text: &str) -> (Option<OrgTodo>, Option<OrgPriority>, String) {
+        let text = text.trim_start();
+        let todo = match text.get(..4) {
+            Some("( ) ") => OrgTodo::TODO,
+            Some("(x) ") => OrgTodo::DONE,
+            Some("(-) ") => OrgTodo::Other("PENDING".into()),
+            Some("(=) ") => OrgTodo::Other("HOLD".into()),
+            Some("(_) ") => OrgTodo::Other("CANCELLED".into()),
+            Some("(!) ") => OrgTodo::Other("URGENT".into()),
+            Some("(?) ") => OrgTodo::Other("UNCERTAIN".into()),
+            _ => return (None, None, text.to_string()),
+        };
+
+        (Some(todo), None, text[4..].trim_start().to_string())
+    }
+
+    fn block_start(&self, line: &str) -> Option<String> {
+        let tag = line.split_whitespace().next()?;
+        let is_block = RANGED_TAGS.iter().any(|x| x.eq_ignore_ascii_case(tag));
+
+        is_block.then(|| format!("{}end", &tag[..1]))
+    }
+
+    fn parse_block(&self, line: &str) -> OrgBlock {
+        let mut words = line.trim()[1..].split_whitespace();
+        let kind = words.next().unwrap_or_default().to_uppercase();
+        let lang = words.next().filter(|_| kind == "CODE").map(String::from);
+
+        OrgBlock {
+            kind,
+            lang,
+            ..Default::default()
+        }
+    }
+}
+
+#[test]
+fn test_norg_format() {
+    let norg = Norg::default();
+    assert_eq!(
+        norg.parse_header("** Header", None).map(|x| x.depth),
+        Some(2)
+    );
+    assert_eq!(
+        norg.parse_todo("(x) Done thing"),
+        (Some(OrgTodo::DONE), None, "Done thing".into())
+    );
+    assert_eq!(norg.parse_todo("Plain"), (None, None, "Plain".into()));
+    assert_eq!(norg.block_start("@code lua"), Some("@end".into()));
+    assert_eq!(norg.block_start("  |example"), Some("|end".into()));
+    assert_eq!(norg.block_start("@alice said hi"), None);
+    assert_eq!(norg.block_start("@end"), None);
+    assert!(norg.is_block_end("@end", "@end"));
+    assert_eq!(norg.parse_block("@code lua").lang.as_deref(), Some("lua"));
+}
@@ -0,0 +1,134 @@
+use serde_json::Value;
+
+use crate::formats::markdown::Markdown;
+use crate::formats::{DocumentFormat, HeaderLine, SourceText};
+use crate::marks::DocType;
+use crate::org::block::OrgBlock;
+
+/// Jupyter notebooks. Markdown cells, and optionally code cells, are searched as a markdown
+/// document. Code cells are fenced so that their comments are not taken as headers.
+pub struct Notebook {
+    extensions: Vec<String>,
+    markdown: Markdown,
+    /// Search code cells too?
+    include_code: bool,
+}
+
+impl Notebook {
+    pub fn new(include_code: bool) -> Notebook {
+        Notebook {
+            extensions: vec!["ipynb".into()],
+            markdown: Markdown::new(vec![]),
+            include_code,
+        }
+    }
+}
+
+impl DocumentFormat for Notebook {
+    fn name(&self) -> &str {
+        "ipynb"
+    }
+
+    fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    fn doc_type(&self) -> DocType {
+        DocType::Markdown
+    }
+
+    fn parse_header(&self, line: &str, next: Option<&str>) -> Option<HeaderLine> {
+        self.markdown.parse_header(line, next)
+    }
+
+    fn block_start(&self, line: &str) -> Option<String> {
+        self.markdown.block_start(line)
+    }
+
+    fn is_block_end(&self, end: &str, line: &str) -> bool {
+        self.markdown.is_block_end(end, line)
+    }
+
+    fn parse_block(&self, line: &str) -> OrgBlock {
+        self.markdown.parse_block(line)
+    }
+
+    fn extract(&self, text: String) -> Result<SourceText, String> {
+        let notebook: Value =
+            serde_json::from_str(&text).map_err(|x| format!("invalid notebook: {}", x))?;
+        let cells = notebook["cells"]
+            .as_array()
+            .ok_or("invalid notebook: there are no cells")?;
+        let metadata = &notebook["metadata"];
+        let language = metadata["language_info"]["name"]
+            .as_str()
+            .or_else(|| metadata["kernelspec"]["language"].as_str())
+            .unwrap_or_default();
+
+        let mut result = SourceText::default();
+        let mut line = 0;
+        for (idx, cell) in cells.iter().enumerate() {
+            let is_code = match cell["cell_type"].as_str() {
+                Some("markdown") => false,
+                Some("code") if self.include_code => true,
+                _ => continue,
+            };
+
+            // Source is either a string or a list of lines, each ending with a newline
+            let mut source = match &cell["source"] {
+                Value::String(x) => x.clone(),
+                Value::Array(xs) => xs.iter().filter_map(|x| x.as_str()).collect(),
+                _ => String::new(),
+            };
+            if !source.is_empty() && !source.ends_with('\n') {
+                source.push('\n');
+            }
+
+            if is_code {
+                result.text.push_str(&format!("```{}\n", language));
+                line += 1;
+            }
+
+            result.cells.push((idx, line));
+            result.text.push_str(&source);
+            line += source.lines().count();
+
+            if is_code {
+                result.text.push_str("```\n");
+                line += 1;
+            }
+
+            // Keep the cells apart, so that paragraphs don't continue from one cell to another
+            result.text.push('\n');
+            line += 1;
+        }
+
+        Ok(result)
+    }
+}
+
+#[test]
+fn test_notebook_extract() {
+    let text = r##"{
+        "metadata": {"language_info": {"name": "python"}},
+        "cells": [
+            {"cell_type": "markdown", "source": ["# Title\n", "Some text"]},
+            {"cell_type": "code", "source": "# comment\nx = 1"},
+            {"cell_type": "markdown", "source": "More"}
+        ]
+    }"##;
+
+    let source = Notebook::new(false).extract(text.into()).unwrap();
+    assert_eq!(source.text, "# Title\nSome text\n\nMore\n\n");
+    assert_eq!(source.locate(1), (Some(0), 1));
+    assert_eq!(source.locate(3), (Some(2), 0));
+
+    let source = Notebook::new(true).extract(text.into()).unwrap();
+    assert_eq!(
+        source.text,
+        "# Title\nSome text\n\n```python\n# comment\nx = 1\n```\n\nMore\n\n"
+    );
+    assert_eq!(source.locate(5), (Some(1), 1));
+
+    assert!(Notebook::new(false).extract("{}".into()).is_err());
+}
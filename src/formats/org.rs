@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use crate::extensions::StartsWithIgnoreCase;
+use crate::formats::{
+    parse_marked_header, parse_org_tags, parse_org_todo, DocumentFormat, HeaderLine,
+};
+use crate::marks::DocType;
+use crate::org::block::OrgBlock;
+use crate::org::header::{OrgPriority, OrgTodo};
+
+/// Org-mode files. Headers start with `*`, blocks are `#+BEGIN_X ... #+END_X`.
+pub struct Org {
+    extensions: Vec<String>,
+    /// Search archive files, like `x.org_archive`, too?
+    include_archives: bool,
+}
+
+impl Org {
+    pub fn new(extensions: Vec<String>, include_archives: bool) -> Org {
+        Org {
+            extensions,
+            include_archives,
+        }
+    }
+}
+
+impl DocumentFormat for Org {
+    fn name(&self) -> &str {
+        "org"
+    }
+
+    fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    fn doc_type(&self) -> DocType {
+        DocType::OrgMode
+    }
+
+    fn parse_header(&self, line: &str, _next: Option<&str>) -> Option<HeaderLine> {
+        parse_marked_header(line, '*')
+    }
+
+    fn parse_tags(&self, text: &str) -> (Vec<String>, String) {
+        parse_org_tags(text)
+    }
+
+    fn parse_todo(&self, text: &str) -> (Option<OrgTodo>, Option<OrgPriority>, String) {
+        let (todo, priority, rest) = parse_org_todo(text);
+
+        // COMMENT is not a TODO keyword, keep it for parse_comment
+        match todo {
+            Some(OrgTodo::Other(x)) if x == "COMMENT" => {
+                (None, priority, format!("{} {}", x, rest))
+            }
+            todo => (todo, priority, rest),
+        }
+    }
+
+    fn parse_comment(&self, text: &str) -> (bool, String) {
+        match text.strip_prefix("COMMENT") {
+            Some(rest) if rest.is_empty() || rest.starts_with(' ') => (true, rest.trim().into()),
+            _ => (false, text.to_string()),
+        }
+    }
+
+    fn has_file_settings(&self) -> bool {
+        true
+    }
+
+    fn has_planning(&self) -> bool {
+        true
+    }
+
+    fn has_drawers(&self) -> bool {
+        true
+    }
+
+    fn block_start(&self, line: &str) -> Option<String> {
+        let line = line.trim();
+        if !line.starts_with_i("#+BEGIN_") {
+            return None;
+        }
+
+        let name = line[8..].split_whitespace().next().unwrap_or_default();
+        Some(format!("#+END_{}", name))
+    }
+
+    fn ends_blocks(&self, line: &str) -> bool {
+        self.parse_header(line, None).is_some()
+    }
+
+    fn parse_block(&self, line: &str) -> OrgBlock {
+        let line = line.trim();
+        let (kind, rest) = line[8..].split_once(' ').unwrap_or((&line[8..], ""));
+        let kind = kind.to_uppercase();
+
+        // Only source blocks have a language, which comes before the header arguments
+        let rest = rest.trim_start();
+        let (lang, args) = match rest.split_once(' ').unwrap_or((rest, "")) {
+            (lang, args) if kind == "SRC" && !lang.is_empty() && !lang.starts_with(':') => {
+                (Some(lang.to_string()), args)
+            }
+            _ => (None, rest),
+        };
+
+        OrgBlock {
+            kind,
+            lang,
+            args: OrgBlock::parse_args(args),
+            ..Default::default()
+        }
+    }
+
+    fn parse_block_name(&self, line: &str) -> Option<String> {
+        let line = line.trim();
+        if !line.starts_with_i("#+NAME:") {
+            return None;
+        }
+
+        Some(line[7..].trim().to_string()).filter(|x| !x.is_empty())
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        path.extension().and_then(|x| x.to_str()).is_some_and(|x| {
+            let x = match x.strip_suffix("_archive") {
+                Some(x) if self.include_archives => x,
+                Some(_) => return false,
+                None => x,
+            };
+            self.extensions.iter().any(|y| x == y)
+        })
+    }
+}
+
+#[test]
+fn test_org_format() {
+    let org = Org::new(vec!["org".into()], false);
+    assert_eq!(
+        org.parse_header("** Header", None).map(|x| x.depth),
+        Some(2)
+    );
+    assert_eq!(org.parse_header("**bold** text", None), None);
+    assert_eq!(org.parse_header(" * list item", None), None);
+    assert_eq!(
+        org.block_start("#+begin_src rust :tangle x.rs"),
+        Some("#+END_src".into())
+    );
+    assert!(org.is_block_end("#+END_src", "  #+end_src"));
+
+    let block = org.parse_block("#+begin_src python :tangle x.py");
+    assert_eq!(block.kind, "SRC");
+    assert_eq!(block.lang.as_deref(), Some("python"));
+    assert_eq!(block.arg("tangle"), Some("x.py"));
+    assert_eq!(org.parse_block("#+BEGIN_QUOTE").lang, None);
+    assert_eq!(org.parse_block_name("#+name: build"), Some("build".into()));
+}
+
+#[test]
+fn test_org_comment() {
+    let org = Org::new(vec!["org".into()], false);
+    let (todo, _, rest) = org.parse_todo("COMMENT Title");
+    assert_eq!(todo, None);
+    assert_eq!(org.parse_comment(&rest), (true, "Title".into()));
+
+    let (todo, _, rest) = org.parse_todo("TODO [#A] COMMENT Title");
+    assert_eq!(todo, Some(OrgTodo::TODO));
+    assert_eq!(org.parse_comment(&rest), (true, "Title".into()));
+    assert_eq!(org.parse_comment("COMMENTS"), (false, "COMMENTS".into()));
+
+    assert!(org.matches(Path::new("x.org")));
+    assert!(!org.matches(Path::new("x.org_archive")));
+    assert!(Org::new(vec!["org".into()], true).matches(Path::new("x.org_archive")));
+}
@@ -0,0 +1,33 @@
+use crate::formats::{DocumentFormat, HeaderLine};
+use crate::marks::DocType;
+
+/// Plain text files. They have no headers or blocks, every line is just a line.
+pub struct Plain {
+    extensions: Vec<String>,
+}
+
+impl Default for Plain {
+    fn default() -> Plain {
+        Plain {
+            extensions: vec!["txt".into(), "text".into()],
+        }
+    }
+}
+
+impl DocumentFormat for Plain {
+    fn name(&self) -> &str {
+        "txt"
+    }
+
+    fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    fn doc_type(&self) -> DocType {
+        DocType::Markdown
+    }
+
+    fn parse_header(&self, _line: &str, _next: Option<&str>) -> Option<HeaderLine> {
+        None
+    }
+}
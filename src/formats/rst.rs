@@ -0,0 +1,81 @@
+use crate::formats::{DocumentFormat, HeaderLine};
+use crate::marks::DocType;
+
+/// Adornment characters, in the order of the depth they are given. reStructuredText decides the
+/// depth by the order the adornments are first seen in the document, this follows the convention
+/// used by Python's documentation instead so that headers can be parsed one by one.
+const ADORNMENTS: [char; 10] = ['#', '*', '=', '-', '^', '"', '~', '\'', '+', '`'];
+
+/// reStructuredText files. Headers are underlined, like `Title` followed by `=====`.
+pub struct Rst {
+    extensions: Vec<String>,
+}
+
+impl Default for Rst {
+    fn default() -> Rst {
+        Rst {
+            extensions: vec!["rst".into(), "rest".into()],
+        }
+    }
+}
+
+impl DocumentFormat for Rst {
+    fn name(&self) -> &str {
+        "rst"
+    }
+
+    fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    fn doc_type(&self) -> DocType {
+        DocType::Markdown
+    }
+
+    fn parse_header(&self, line: &str, next: Option<&str>) -> Option<HeaderLine> {
+        let text = line.trim_end();
+        if text.is_empty() || text.starts_with(char::is_whitespace) || adornment(text).is_some() {
+            return None;
+        }
+
+        let underline = next?.trim_end();
+        let chr = adornment(underline)?;
+        if underline.chars().count() < text.chars().count().min(3) {
+            return None;
+        }
+
+        let depth = ADORNMENTS
+            .iter()
+            .position(|x| *x == chr)
+            .unwrap_or(ADORNMENTS.len())
+            + 1;
+
+        Some(HeaderLine {
+            depth,
+            text: text.to_string(),
+            is_underlined: true,
+        })
+    }
+
+    // Literal blocks are only delimited by indentation, so they are not tracked
+}
+
+/// If the line is an adornment, like `=====`, return the character it's made of.
+fn adornment(line: &str) -> Option<char> {
+    let chr = line.chars().next().filter(|x| x.is_ascii_punctuation())?;
+    (line.len() >= 2 && line.chars().all(|x| x == chr)).then_some(chr)
+}
+
+#[test]
+fn test_rst_format() {
+    let rst = Rst::default();
+    let header = rst.parse_header("Title", Some("=====")).unwrap();
+    assert_eq!((header.depth, header.text.as_str()), (3, "Title"));
+    assert_eq!(
+        rst.parse_header("Sub", Some("---")).map(|x| x.depth),
+        Some(4)
+    );
+    assert_eq!(rst.parse_header("Text", Some("more text")), None);
+    assert_eq!(rst.parse_header("=====", Some("=====")), None);
+    assert_eq!(rst.parse_header("A long title", Some("=")), None);
+}
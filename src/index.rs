@@ -0,0 +1,539 @@
+//! A persistent index of the searched files, see `marks index`. The index keeps the parsed headers
+//! of every file along with the words in them, so that a search can skip the files that can't
+//! match without walking the tree and reading every file. Files are still searched as usual once
+//! they are picked.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::args::Args;
+use crate::formats::BlockTracker;
+use crate::marks::{Marks, IGNORE_FILES};
+use crate::markup::{self, words};
+use crate::rank::{self, CorpusStats};
+
+/// Version of the index format. Indexes of other versions are ignored.
+const VERSION: u32 = 3;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    /// The directory that is indexed.
+    pub root: PathBuf,
+    /// Options that change which files are searched and how they are read. The index is not used
+    /// for searches with other options.
+    options: String,
+    /// Indexed directories, their ignore files and the setup files of the indexed files with their
+    /// modification times. Adding, removing or renaming a file changes the time of its directory.
+    dirs: Vec<(PathBuf, Stamp)>,
+    pub files: Vec<FileEntry>,
+    /// Lowercased words of the files, with the indices of the files in `files` that contain them
+    /// and the number of sections in each file that contain them, see [`crate::rank`].
+    pub terms: HashMap<String, Vec<(u32, u32)>>,
+}
+
+/// Modification time of a file or a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    secs: u64,
+    nanos: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    modified: Stamp,
+    size: u64,
+    hash: [u8; 32],
+    /// Setup files of the file with their modification times. They change the TODO keywords and
+    /// the tags of the file, so the file is parsed again when any of them changes.
+    setup_files: Vec<(PathBuf, Stamp)>,
+    pub headers: Vec<IndexedHeader>,
+    /// Tags of the headers, along with the file tags.
+    tags: Vec<String>,
+    /// Letters and digits in the file, see [`letter_set`].
+    letters: u64,
+    /// Number of sections in the file that have any words.
+    sections: u32,
+    /// Sum of the weighted lengths of the sections.
+    length: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedHeader {
+    pub line: usize,
+    pub depth: usize,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub todo: Option<String>,
+    pub priority: Option<String>,
+}
+
+/// What has changed while updating the index.
+#[derive(Debug, Default)]
+pub struct UpdateStats {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+impl SearchIndex {
+    /// Index every file in the tree.
+    pub fn build(app: &Marks) -> SearchIndex {
+        SearchIndex::update(app, None).0
+    }
+
+    /// Index the files in the tree, reusing the entries of the files in `previous` that have not
+    /// changed. Files whose modification time or size has changed are hashed, and only parsed
+    /// again if their contents have changed too.
+    pub fn update(app: &Marks, previous: Option<SearchIndex>) -> (SearchIndex, UpdateStats) {
+        let previous = previous.filter(|x| x.options == options_key(app));
+        let old_ids = previous.as_ref().map_or_else(HashMap::new, |x| {
+            x.files
+                .iter()
+                .enumerate()
+                .map(|(idx, file)| (file.path.as_path(), idx))
+                .collect()
+        });
+        let old_files = previous.as_ref().map_or(&[][..], |x| x.files.as_slice());
+
+        // Changing an ignore file may change which files are searched
+        let mut dirs: Vec<_> = app
+            .find_dirs()
+            .flat_map(|x| {
+                let ignores = IGNORE_FILES.map(|name| x.path().join(name));
+                std::iter::once(x.into_path()).chain(ignores)
+            })
+            .filter_map(|x| {
+                let stamp = stamp(&x)?.0;
+                Some((x, stamp))
+            })
+            .collect();
+        let paths = app.find_files().map(|x| x.into_path()).collect::<Vec<_>>();
+
+        // Every file is either reused from the previous index or parsed along with its words
+        let indexed = paths
+            .par_iter()
+            .filter_map(|path| {
+                let (modified, size) = stamp(path)?;
+                let old = old_ids.get(path.as_path()).map(|x| (*x, &old_files[*x]));
+                let old = old.filter(|(_, x)| x.is_setup_fresh());
+                if let Some((idx, entry)) =
+                    old.filter(|(_, x)| x.modified == modified && x.size == size)
+                {
+                    return Some((entry.clone(), Ok(idx)));
+                }
+
+                let bytes = fs::read(path).ok()?;
+                let hash = *blake3::hash(&bytes).as_bytes();
+                if let Some((idx, entry)) = old.filter(|(_, x)| x.hash == hash) {
+                    let entry = FileEntry {
+                        modified,
+                        size,
+                        ..entry.clone()
+                    };
+                    return Some((entry, Ok(idx)));
+                }
+
+                let (entry, terms) = parse_file(app, path, &bytes)?;
+                let entry = FileEntry {
+                    modified,
+                    size,
+                    hash,
+                    ..entry
+                };
+                Some((entry, Err((terms, old_ids.contains_key(path.as_path())))))
+            })
+            .collect::<Vec<_>>();
+
+        let mut stats = UpdateStats::default();
+        let mut new_ids = vec![None; old_files.len()];
+        let mut new_terms = vec![];
+        let mut files = vec![];
+        for (new_idx, (entry, source)) in indexed.into_iter().enumerate() {
+            match source {
+                Ok(old_idx) => {
+                    new_ids[old_idx] = Some(new_idx as u32);
+                    stats.unchanged += 1;
+                }
+                Err((terms, is_updated)) => {
+                    new_terms.push((new_idx as u32, terms));
+                    if is_updated {
+                        stats.updated += 1;
+                    } else {
+                        stats.added += 1;
+                    }
+                }
+            }
+            files.push(entry);
+        }
+        stats.removed = old_files.len() - stats.unchanged - stats.updated;
+
+        let mut setup_files = files
+            .iter()
+            .flat_map(|x| x.setup_files.iter().cloned())
+            .collect::<Vec<_>>();
+        setup_files.sort_unstable_by(|x, y| x.0.cmp(&y.0));
+        setup_files.dedup_by(|x, y| x.0 == y.0);
+        dirs.extend(setup_files);
+
+        // Keep the words of the reused files, then add the words of the parsed ones
+        let mut terms = HashMap::new();
+        for (term, ids) in previous.map(|x| x.terms).unwrap_or_default() {
+            let ids = ids
+                .iter()
+                .filter_map(|(x, sections)| Some((new_ids[*x as usize]?, *sections)))
+                .collect::<Vec<_>>();
+            if !ids.is_empty() {
+                terms.insert(term, ids);
+            }
+        }
+        for (idx, file_terms) in new_terms {
+            for (term, sections) in file_terms {
+                terms
+                    .entry(term)
+                    .or_insert_with(Vec::new)
+                    .push((idx, sections));
+            }
+        }
+        terms.values_mut().for_each(|x| x.sort_unstable());
+
+        let index = SearchIndex {
+            version: VERSION,
+            root: app.args.path.clone(),
+            options: options_key(app),
+            dirs,
+            files,
+            terms,
+        };
+        (index, stats)
+    }
+
+    /// Load the index of the searched directory, if there is one.
+    pub fn load(args: &Args) -> Option<SearchIndex> {
+        let bytes = fs::read(index_path(args)).ok()?;
+        let index: SearchIndex = bincode::deserialize(&bytes).ok()?;
+        (index.version == VERSION && index.root == args.path).then_some(index)
+    }
+
+    /// Write the index into the cache directory.
+    pub fn save(&self, args: &Args) -> io::Result<()> {
+        let path = index_path(args);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write to a temporary file first, so that a search never reads a half written index
+        let bytes = bincode::serialize(self).map_err(io::Error::other)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes)?;
+        fs::rename(tmp, path)
+    }
+
+    /// Is the index still up to date for a search with the given options? No file or directory
+    /// may have changed since the index is built.
+    pub fn is_fresh(&self, app: &Marks) -> bool {
+        self.options == options_key(app)
+            && self
+                .dirs
+                .par_iter()
+                .all(|(path, modified)| stamp(path).is_some_and(|x| x.0 == *modified))
+            && self
+                .files
+                .par_iter()
+                .all(|file| stamp(&file.path).is_some_and(|x| x == (file.modified, file.size)))
+    }
+
+    /// Paths of the files that may match the query and the filters of the search. Others can't
+    /// match, so there is no need to search them.
+    pub fn candidates(&self, app: &Marks) -> Vec<PathBuf> {
+        let args = app.args;
+        let query = &app.query;
+
+        // Included files are not indexed as a part of the files that include them
+        if args.follow_includes {
+            return self.files.iter().map(|x| x.path.clone()).collect();
+        }
+
+        // Every quoted term should be in the file, so every word in it should be a part of a
+        // word in the file
+        let mut must_files: Option<Vec<bool>> = None;
+        for word in query.musts.iter().flat_map(|x| words(x)) {
+            let mut has_word = vec![false; self.files.len()];
+            for (term, ids) in self.terms.iter() {
+                if term.contains(word.as_str()) {
+                    ids.iter().for_each(|(x, _)| has_word[*x as usize] = true);
+                }
+            }
+
+            must_files = Some(match must_files {
+                Some(x) => x.iter().zip(has_word).map(|(x, y)| *x && y).collect(),
+                None => has_word,
+            });
+        }
+
+        // Fuzzy terms match if the letters of any of them are in the file
+        let fuzzy_letters = query.rest.iter().map(|x| letter_set(x)).collect::<Vec<_>>();
+
+        self.files
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| must_files.as_ref().is_none_or(|x| x[*idx]))
+            .filter(|(_, file)| {
+                fuzzy_letters.is_empty() || fuzzy_letters.iter().any(|x| file.letters & x == *x)
+            })
+            .filter(|(_, file)| args.tagged.iter().all(|x| file.tags.contains(x)))
+            .filter(|(_, file)| {
+                args.todo.is_empty()
+                    || file.headers.iter().any(|header| {
+                        let todo = header.todo.as_deref();
+                        args.todo.iter().any(|x| todo == Some(x.keyword()))
+                    })
+            })
+            .map(|(_, file)| file.path.clone())
+            .collect()
+    }
+}
+
+impl FileEntry {
+    /// Have none of the setup files of the file changed since it was parsed?
+    fn is_setup_fresh(&self) -> bool {
+        self.setup_files
+            .iter()
+            .all(|(path, modified)| stamp(path).is_some_and(|x| x.0 == *modified))
+    }
+}
+
+impl SearchIndex {
+    /// Statistics of all sections in the indexed files, for ranking the results with the given
+    /// query terms.
+    pub fn corpus_stats(&self, terms: &[String]) -> CorpusStats {
+        CorpusStats {
+            sections: self.files.iter().map(|x| x.sections as usize).sum(),
+            length: self.files.iter().map(|x| x.length).sum(),
+            freqs: terms
+                .iter()
+                .map(|term| {
+                    let files = self.terms.get(term).map_or(&[][..], |x| x.as_slice());
+                    files.iter().map(|(_, sections)| *sections as usize).sum()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Parse the file into its index entry and its words. Modification time, size and hash of the
+/// entry are left for the caller to fill in.
+fn parse_file(app: &Marks, path: &Path, bytes: &[u8]) -> Option<(FileEntry, Vec<(String, u32)>)> {
+    let format = app.format_of(path)?;
+    let source = app.extract_source(path, format, bytes)?;
+    let settings = app.read_settings(path, format, &source);
+    let document = app.parse_source(path, format, &source, &settings);
+    let doc_type = format.doc_type();
+
+    // Matchers see the text without the markup, the raw text is indexed too just to be safe
+    let filename = path.file_name()?.to_string_lossy();
+    let text = source
+        .text
+        .lines()
+        .flat_map(|x| [x.to_string(), markup::strip(x, &doc_type)])
+        .chain(std::iter::once(filename.to_string()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Sections are counted the way the search counts them for ranking, without the markup and
+    // with the header words weighted. The lines that belong to a header, like its properties, are
+    // not a part of the section
+    let mut sections = vec![(HashSet::new(), 0.0)];
+    let mut blocks = BlockTracker::default();
+    let mut lines = source.text.lines().enumerate().peekable();
+    while let Some((idx, line)) = lines.next() {
+        let rest = lines.clone().map(|(_, x)| x);
+        let header = if blocks.push(format, idx, line, rest) {
+            None
+        } else {
+            app.parse_header(&mut lines, format, &settings, line, idx)
+        };
+        let (text, weight) = match &header {
+            Some(header) => {
+                sections.push((HashSet::new(), 0.0));
+                (header.content.as_str(), rank::HEADER_WEIGHT)
+            }
+            None => (line, 1.0),
+        };
+
+        let (section_words, length) = sections.last_mut()?;
+        for word in words(&markup::strip(text, &doc_type)) {
+            section_words.insert(word);
+            *length += weight;
+        }
+    }
+    sections.retain(|(_, length)| *length > 0.0);
+
+    let mut section_counts = HashMap::new();
+    for word in sections.iter().flat_map(|(x, _)| x) {
+        *section_counts.entry(word.as_str()).or_insert(0) += 1;
+    }
+
+    let mut terms = words(&text);
+    terms.sort_unstable();
+    terms.dedup();
+    let terms = terms
+        .into_iter()
+        .map(|x| {
+            let count = section_counts.get(x.as_str()).copied().unwrap_or(0);
+            (x, count)
+        })
+        .collect();
+
+    let mut tags = settings.filetags.clone();
+    tags.extend(document.headers.iter().flat_map(|x| x.tags.iter().cloned()));
+    tags.sort_unstable();
+    tags.dedup();
+
+    let headers = document
+        .headers
+        .iter()
+        .map(|x| IndexedHeader {
+            line: x.line,
+            depth: x.depth,
+            content: x.content.clone(),
+            tags: x.tags.clone(),
+            todo: x.todo.as_ref().map(|x| x.keyword().to_string()),
+            priority: x.priority.as_ref().map(|x| x.0.clone()),
+        })
+        .collect();
+
+    let entry = FileEntry {
+        path: path.to_path_buf(),
+        modified: Stamp { secs: 0, nanos: 0 },
+        size: 0,
+        hash: [0; 32],
+        setup_files: settings
+            .setup_files
+            .iter()
+            .filter_map(|x| Some((x.clone(), stamp(x)?.0)))
+            .collect(),
+        headers,
+        tags,
+        letters: letter_set(&text),
+        sections: sections.len() as u32,
+        length: sections.iter().map(|(_, x)| x).sum(),
+    };
+    Some((entry, terms))
+}
+
+/// Options that change which files are indexed and what is indexed for them.
+fn options_key(app: &Marks) -> String {
+    let formats = app
+        .formats
+        .iter()
+        .map(|x| format!("{}:{}", x.name(), x.extensions().join(",")))
+        .collect::<Vec<_>>();
+    let args = app.args;
+
+    format!(
+        "{:?} {:?} {} {} {} {} {:?} {:?}",
+        formats,
+        args.blacklist_folder,
+        args.include_archived,
+        args.notebook_code,
+        args.no_ignore,
+        args.hidden,
+        args.glob,
+        args.iglob
+    )
+}
+
+/// Where the index of the searched directory is kept: `--cache-dir`, `$XDG_CACHE_HOME/marks` or
+/// `~/.cache/marks`, in a file named after the directory.
+pub fn index_path(args: &Args) -> PathBuf {
+    let dir = match &args.cache_dir {
+        Some(dir) => dir.clone(),
+        None => std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir)
+            .join("marks"),
+    };
+
+    let root = args.path.to_string_lossy();
+    let name = blake3::hash(root.as_bytes()).to_hex();
+    dir.join(format!("{}.idx", &name[..16]))
+}
+
+/// Modification time and size of the file at the given path.
+fn stamp(path: &Path) -> Option<(Stamp, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let time = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    let stamp = Stamp {
+        secs: time.as_secs(),
+        nanos: time.subsec_nanos(),
+    };
+    Some((stamp, metadata.len()))
+}
+
+/// A set of the ASCII letters and digits in the text, ignoring case. Each of them is a bit.
+fn letter_set(text: &str) -> u64 {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(|x| match x {
+            'a'..='z' => Some(x as u32 - 'a' as u32),
+            '0'..='9' => Some(26 + x as u32 - '0' as u32),
+            _ => None,
+        })
+        .fold(0, |set, x| set | (1 << x))
+}
+
+#[test]
+fn test_letter_set() {
+    assert_eq!(letter_set("ab") & letter_set("ba"), letter_set("ab"));
+    assert_ne!(letter_set("abc") & letter_set("x"), letter_set("x"));
+}
+
+#[test]
+fn test_update() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, text: &str| fs::write(dir.path().join(name), text).unwrap();
+    write(
+        "a.org",
+        "* Alpha\nSCHEDULED: <2024-01-01 Mon>\n:PROPERTIES:\n:ID: x\n:END:\nalpha\n",
+    );
+    write("b.org", "#+SETUPFILE: todo.setup\n* WAIT beta\n");
+    write("todo.setup", "#+TODO: WAIT | DONE\n");
+
+    let path = dir.path().to_str().unwrap();
+    let args = Args::parse_from(["marks", "x", path]);
+    let app = Marks::new(&args);
+    let todo_of = |index: &SearchIndex, name: &str| {
+        let file = index.files.iter().find(|x| x.path.ends_with(name)).unwrap();
+        file.headers[0].todo.clone()
+    };
+
+    let index = SearchIndex::build(&app);
+    assert!(index.is_fresh(&app));
+    assert_eq!(todo_of(&index, "b.org"), Some("WAIT".to_string()));
+    // The planning line and the properties belong to the header, not to its section
+    let stats = index.corpus_stats(&["alpha".to_string(), "id".to_string()]);
+    assert_eq!((stats.sections, stats.freqs), (3, vec![1, 0]));
+
+    // Changing the setup file makes the index stale, and only the file that uses it is parsed
+    write("todo.setup", "#+TODO: NEXT | DONE\n");
+    assert!(!index.is_fresh(&app));
+    let (index, stats) = SearchIndex::update(&app, Some(index));
+    assert_eq!((stats.updated, stats.unchanged), (1, 1));
+    assert_eq!(todo_of(&index, "b.org"), None);
+    assert!(index.is_fresh(&app));
+
+    write("c.org", "* Gamma\n");
+    assert!(!index.is_fresh(&app));
+    let (_, stats) = SearchIndex::update(&app, Some(index));
+    assert_eq!((stats.added, stats.unchanged), (1, 2));
+}
@@ -6,3 +6,14 @@ pub mod utils;
 pub mod result;
 pub mod args;
 pub mod marks;
+pub mod links;
+pub mod commands;
+pub mod markup;
+pub mod formats;
+pub mod index;
+pub mod rank;
+pub mod matcher;
+pub mod boost;
+pub mod explain;
+pub mod sort;
+pub mod prefilter;
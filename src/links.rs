@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::marks::DocType;
+use crate::org::document::OrgDocument;
+use crate::org::link::{LinkSearch, LinkTarget, OrgLink};
+use crate::utils::file_utils;
+
+/// What a link points to, after resolving it against the walked files.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Resolution {
+    /// Points to something outside of the notes, like a web page.
+    External,
+    /// Points to a whole document. Contains the index of the document.
+    File(usize),
+    /// Points to a header. Contains the index of the document and the index of the header.
+    Header(usize, usize),
+    /// Points to a line in a document. Contains the index of the document and the line index.
+    Line(usize, usize),
+    /// Points to a file that exists but is not one of the searched documents, like an image.
+    Asset(PathBuf),
+    /// The linked file does not exist.
+    MissingFile(PathBuf),
+    /// The linked document exists but the header or anchor in it does not.
+    MissingSearch(usize, LinkSearch),
+    /// No header has the given ID property.
+    UnknownId(String),
+    /// No document has the given name.
+    UnknownNote(String),
+}
+
+impl Resolution {
+    /// The index of the document this resolution points to, if it points to one.
+    pub fn document(&self) -> Option<usize> {
+        match self {
+            Resolution::File(x) | Resolution::Header(x, _) | Resolution::Line(x, _) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Does this resolution point to nothing?
+    pub fn is_broken(&self) -> bool {
+        matches!(
+            self,
+            Resolution::MissingFile(_)
+                | Resolution::MissingSearch(_, _)
+                | Resolution::UnknownId(_)
+                | Resolution::UnknownNote(_)
+        )
+    }
+
+    /// Is `self` the same as `target` or something that resides in `target`?
+    fn points_into(&self, target: &Resolution) -> bool {
+        match target {
+            Resolution::File(x) => self.document() == Some(*x),
+            _ => self == target,
+        }
+    }
+}
+
+/// All documents in the walked tree, indexed in a way that links between them can be resolved.
+pub struct LinkIndex<'a> {
+    pub documents: Vec<OrgDocument<'a>>,
+    by_path: HashMap<PathBuf, usize>,
+    /// Lowercase file names without extension, used for wiki-style links.
+    by_name: HashMap<String, Vec<usize>>,
+    /// ID properties, points to (document index, header index).
+    by_id: HashMap<String, (usize, usize)>,
+}
+
+impl<'a> LinkIndex<'a> {
+    pub fn new(mut documents: Vec<OrgDocument<'a>>) -> LinkIndex<'a> {
+        // Keep the output stable regardless of the order files are parsed in.
+        documents.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut by_path = HashMap::new();
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_id = HashMap::new();
+
+        for (doc_idx, doc) in documents.iter().enumerate() {
+            by_path.insert(doc.path.clone(), doc_idx);
+
+            if let Some(name) = doc.path.file_stem().and_then(|x| x.to_str()) {
+                by_name
+                    .entry(name.to_lowercase())
+                    .or_default()
+                    .push(doc_idx);
+            }
+
+            for (header_idx, header) in doc.headers.iter().enumerate() {
+                if let Some(id) = header.properties.get("ID") {
+                    by_id.insert(id.trim().to_string(), (doc_idx, header_idx));
+                }
+            }
+        }
+
+        LinkIndex {
+            documents,
+            by_path,
+            by_name,
+            by_id,
+        }
+    }
+
+    /// Resolve the given link found in the document with index `doc_idx`.
+    pub fn resolve(&self, doc_idx: usize, link: &OrgLink) -> Resolution {
+        let doc = &self.documents[doc_idx];
+        match &link.target {
+            LinkTarget::External(_) => Resolution::External,
+            LinkTarget::Id(id) => self.resolve_id(id),
+            LinkTarget::Internal(search) => match self.resolve_search(doc_idx, search) {
+                // Org's [[Note Name]] links may also point to other notes, as in wikis.
+                Resolution::MissingSearch(_, LinkSearch::Text(name))
+                    if matches!(doc.doc_type, DocType::OrgMode) =>
+                {
+                    let resolved = self.resolve_note(&name, None);
+                    resolved.unwrap_or(Resolution::MissingSearch(doc_idx, LinkSearch::Text(name)))
+                }
+                x => x,
+            },
+            LinkTarget::Wiki { name, search } => self
+                .resolve_note(name, search.as_ref())
+                .unwrap_or_else(|| Resolution::UnknownNote(name.clone())),
+            LinkTarget::File { path, search } => {
+                let base = doc.path.parent().unwrap_or_else(|| Path::new("/"));
+                self.resolve_file(&file_utils::resolve_path(base, path), search.as_ref())
+            }
+        }
+    }
+
+    /// Resolve a target given on the command line. It may be a file path (`x.org`), a file path
+    /// with a header (`x.org::*Heading`, `x.md#anchor`), a header in any file (`*Heading`) or an
+    /// ID (`id:...`). Relative paths are resolved against `base`.
+    pub fn resolve_target(&self, base: &Path, target: &str) -> Vec<Resolution> {
+        if let Some(id) = target.strip_prefix("id:") {
+            return vec![self.resolve_id(id.trim())];
+        }
+
+        if let Some(heading) = target.strip_prefix('*') {
+            return self
+                .documents
+                .iter()
+                .enumerate()
+                .filter_map(|(doc_idx, doc)| {
+                    doc.find_header(heading)
+                        .map(|x| Resolution::Header(doc_idx, x))
+                })
+                .collect();
+        }
+
+        let (path, search) = if let Some((path, search)) = target.split_once("::") {
+            (path, Some(LinkSearch::from_org(search)))
+        } else if let Some((path, anchor)) = target.split_once('#') {
+            (path, Some(LinkSearch::Anchor(anchor.into())))
+        } else {
+            (target, None)
+        };
+
+        // Paths of the documents are canonical, so the target should be too, like when `base` is
+        // under a symlink
+        let path = file_utils::resolve_path(base, path);
+        let path = path.canonicalize().unwrap_or(path);
+        vec![self.resolve_file(&path, search.as_ref())]
+    }
+
+    /// Find every link that points to one of the given targets. Returns (document index, link index) pairs.
+    pub fn backlinks(&self, targets: &[Resolution]) -> Vec<(usize, usize)> {
+        self.links()
+            .filter(|(doc_idx, link_idx)| {
+                let resolved = self.resolve(*doc_idx, &self.documents[*doc_idx].links[*link_idx]);
+                targets.iter().any(|target| resolved.points_into(target))
+            })
+            .collect()
+    }
+
+    /// Resolve every link in every document. Returns (document index, link, resolution) triples.
+    pub fn resolved_links(&self) -> impl Iterator<Item = (usize, &OrgLink, Resolution)> + '_ {
+        self.links().map(move |(doc_idx, link_idx)| {
+            let link = &self.documents[doc_idx].links[link_idx];
+            (doc_idx, link, self.resolve(doc_idx, link))
+        })
+    }
+
+    /// Iterate over all links in all documents as (document index, link index) pairs.
+    pub fn links(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.documents
+            .iter()
+            .enumerate()
+            .flat_map(|(doc_idx, doc)| {
+                (0..doc.links.len()).map(move |link_idx| (doc_idx, link_idx))
+            })
+    }
+
+    fn resolve_id(&self, id: &str) -> Resolution {
+        match self.by_id.get(id) {
+            Some((doc_idx, header_idx)) => Resolution::Header(*doc_idx, *header_idx),
+            None => Resolution::UnknownId(id.into()),
+        }
+    }
+
+    fn resolve_note(&self, name: &str, search: Option<&LinkSearch>) -> Option<Resolution> {
+        let doc_idx = *self.by_name.get(&name.trim().to_lowercase())?.first()?;
+        Some(match search {
+            Some(search) => self.resolve_search(doc_idx, search),
+            None => Resolution::File(doc_idx),
+        })
+    }
+
+    fn resolve_file(&self, path: &Path, search: Option<&LinkSearch>) -> Resolution {
+        match (self.by_path.get(path), search) {
+            (Some(doc_idx), Some(search)) => self.resolve_search(*doc_idx, search),
+            (Some(doc_idx), None) => Resolution::File(*doc_idx),
+            (None, _) if path.exists() => Resolution::Asset(path.to_path_buf()),
+            (None, _) => Resolution::MissingFile(path.to_path_buf()),
+        }
+    }
+
+    fn resolve_search(&self, doc_idx: usize, search: &LinkSearch) -> Resolution {
+        let doc = &self.documents[doc_idx];
+        let header = match search {
+            LinkSearch::Heading(heading) => doc.find_header(heading),
+            LinkSearch::Anchor(anchor) => doc.find_anchor(anchor),
+            // Like org, prefer <<targets>> over headers
+            LinkSearch::Text(text) => match doc.find_target(text) {
+                Some(target) => return Resolution::Line(doc_idx, target.line),
+                None => doc.find_header(text),
+            },
+            LinkSearch::Line(line) => return Resolution::Line(doc_idx, line.saturating_sub(1)),
+        };
+
+        match header {
+            Some(header_idx) => Resolution::Header(doc_idx, header_idx),
+            None => Resolution::MissingSearch(doc_idx, search.clone()),
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resolve_target() {
+    use crate::args::Args;
+    use crate::commands::build_link_index;
+    use crate::marks::Marks;
+
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes");
+    std::fs::create_dir(&notes).unwrap();
+    std::fs::write(notes.join("a.org"), "* A\n[[file:b.org][b]]\n").unwrap();
+    std::fs::write(notes.join("b.org"), "* B\n").unwrap();
+    let link = dir.path().join("link");
+    std::os::unix::fs::symlink(&notes, &link).unwrap();
+
+    let args = Args::parse_from(["marks", "x", link.to_str().unwrap()]);
+    let app = Marks::new(&args);
+    let index = build_link_index(&app);
+    let targets = index.resolve_target(&link, "b.org");
+    assert!(matches!(targets.as_slice(), [Resolution::File(_)]));
+    assert_eq!(index.backlinks(&targets).len(), 1);
+}
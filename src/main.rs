@@ -1,46 +1,223 @@
 use itertools::Itertools;
 use rayon::prelude::*;
-use structopt::StructOpt;
+use structopt::clap::{Error, ErrorKind};
 use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
 
-use marks::args::Args;
+use marks::args::{Args, Command, Rank, SortKey};
+use marks::commands;
+use marks::formats;
+use marks::index::SearchIndex;
 use marks::marks::Marks; // TODO: what
+use marks::rank::{self, CorpusStats};
+use marks::result::SearchResult;
+use marks::sort::Sorter;
+
+/// How many results may wait to be printed while the files are being searched.
+const RESULTS_BUFFER: usize = 1024;
 
 fn main() -> Result<(), io::Error> {
-    let args = Args::from_args();
-    let app = Marks::new(&args);
+    let args = Args::parse();
+    if let Err(err) = args.validate() {
+        Error::with_description(&err, ErrorKind::InvalidValue).exit();
+    }
+    let mut app = Marks::new(&args);
 
     if args.debug {
         println!("{:#?}", app.args);
     }
 
-    let mut results = app
-        .find_files()
-        .collect::<Vec<_>>()
-        .par_iter()
-        .filter_map(|f| app.search_file(&f))
-        .flatten()
-        .collect::<Vec<_>>();
+    if let Some(cmd) = &args.cmd {
+        return match cmd {
+            Command::Backlinks { target } => commands::backlinks::run(&app, target),
+            Command::CheckLinks => commands::check_links::run(&app),
+            Command::Graph { format } => commands::graph::run(&app, format),
+            Command::Orphans => commands::orphans::run(&app),
+            Command::Targets => commands::targets::run(&app),
+            Command::Index { action } => commands::index::run(&app, action),
+            Command::Table { export, table } => commands::table::run(&app, export, table),
+        };
+    }
+
+    if args.type_list {
+        for format in formats::all(&args) {
+            let globs = format.extensions().iter().map(|x| format!("*.{}", x)).join(", ");
+            println!("{}: {}", format.name(), globs);
+        }
+        return Ok(());
+    }
+
+    if args.query.is_none() {
+        Error::with_description(
+            "The following required arguments were not provided:\n    <query>",
+            ErrorKind::MissingRequiredArgument,
+        )
+        .exit();
+    }
+
+    // Use the index while it's fresh, otherwise walk the tree. --explain-file only searches the
+    // given file.
+    let index = if args.no_index || args.explain_file.is_some() {
+        None
+    } else {
+        SearchIndex::load(&args)
+    };
+    let index = index.filter(|x| x.is_fresh(&app));
+
+    // Files that are skipped thanks to the index are still a part of the corpus
+    let terms = rank::query_terms(&app.query);
+    let index_corpus = index
+        .as_ref()
+        .filter(|_| args.rank == Rank::Bm25)
+        .map(|x| x.corpus_stats(&terms));
+    let sorter = Sorter::new(&args);
+
+    // BM25 scores need the statistics of every file, which are known beforehand only with the
+    // index. Otherwise every file is searched before any result is scored.
+    if args.explain_file.is_some() || (args.rank == Rank::Bm25 && index_corpus.is_none()) {
+        // Files without results count in the statistics too, and every section of the explained
+        // file is reported
+        app.prefilter = None;
+        let files = match &args.explain_file {
+            Some(file) => vec![file.clone()],
+            None => app.find_files().map(|x| x.into_path()).collect(),
+        };
+
+        let searched = files
+            .par_iter()
+            .filter_map(|f| app.search_file(f))
+            .collect::<Vec<_>>();
+
+        if let Some(file) = &args.explain_file {
+            if searched.is_empty() {
+                let err = format!("can't search `{}`, see --type", file.display());
+                Error::with_description(&err, ErrorKind::InvalidValue).exit();
+            }
+
+            for section in searched.iter().flat_map(|x| &x.trace) {
+                let header = section.header.as_deref().unwrap_or("(before the first header)");
+                match section.reasons.as_slice() {
+                    [] => println!("{}:{}:{}: searched", file.display(), section.line, header),
+                    reasons => println!(
+                        "{}:{}:{}: skipped, {}",
+                        file.display(),
+                        section.line,
+                        header,
+                        reasons.join("; ")
+                    ),
+                }
+            }
+        }
+
+        let corpus = searched
+            .iter()
+            .fold(CorpusStats::new(&terms), |x, y| x.merge(&y.corpus));
+        let mut results = searched
+            .into_iter()
+            .flat_map(|x| x.results)
+            .collect::<Vec<_>>();
+        for result in results.iter_mut() {
+            score(&app, result, &corpus, &terms);
+        }
+
+        sorter.sort(&mut results);
+        print(results.into_iter(), &args);
+        return Ok(());
+    }
+
+    // Results are sent to this thread as soon as they are found. Searching stops once the
+    // results are printed, which may be before every file is searched with --sort none.
+    let corpus = index_corpus.unwrap_or_default();
+    let is_done = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::sync_channel(RESULTS_BUFFER);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            // Stopping early is not an error
+            let _ = match &index {
+                Some(index) => index
+                    .candidates(&app)
+                    .par_iter()
+                    .try_for_each_with(sender, |s, x| send_results(&app, &is_done, s, x)),
+                None => app
+                    .find_files()
+                    .par_bridge()
+                    .try_for_each_with(sender, |s, x| send_results(&app, &is_done, s, x.path())),
+            };
+        });
 
-    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        let results = receiver.into_iter().map(|mut x| {
+            score(&app, &mut x, &corpus, &terms);
+            x
+        });
 
-    let mut iter: Box<dyn Iterator<Item = _>> = Box::new(results.iter_mut());
+        // With --count, only the results that may be printed are kept while sorting
+        match (args.sort, args.count) {
+            (SortKey::None, _) => print(results, &args),
+            (_, Some(count)) if !args.only_headers => {
+                print(sorter.top(results, count).into_iter(), &args)
+            }
+            _ => {
+                let mut results = results.collect::<Vec<_>>();
+                sorter.sort(&mut results);
+                print(results.into_iter(), &args);
+            }
+        }
+        is_done.store(true, AtomicOrdering::Relaxed);
+    });
+
+    Ok(())
+}
+
+/// Search the file and send its results. Fails once the results are not needed anymore.
+fn send_results<'a>(
+    app: &Marks<'a>,
+    is_done: &AtomicBool,
+    sender: &mut SyncSender<SearchResult<'a>>,
+    file: &Path,
+) -> Result<(), ()> {
+    if is_done.load(AtomicOrdering::Relaxed) {
+        return Err(());
+    }
+    for result in app.search_file(file).map_or(vec![], |x| x.results) {
+        sender.send(result).map_err(|_| ())?;
+    }
+    Ok(())
+}
+
+/// Set the final score of the result, from its BM25 score with --rank bm25 and its boosts.
+fn score(app: &Marks, result: &mut SearchResult, corpus: &CorpusStats, terms: &[String]) {
+    if app.args.rank == Rank::Bm25 {
+        let score = result.section.as_ref().map_or(0.0, |x| corpus.score(x));
+        result.base_score = (score * 1000.0).round() as i64;
+    }
+    if let (Some(explanation), Some(section)) = (&mut result.explanation, &result.section) {
+        explanation.bm25 = terms.iter().cloned().zip(corpus.term_scores(section)).collect();
+    }
+
+    let score = result.base_score as f64 * result.signals.multiplier(&app.weights);
+    result.score = score.round() as i64;
+}
+
+/// Print the results in the given order, with --only-headers and --count.
+fn print<'a: 'i, 'i>(results: impl Iterator<Item = SearchResult<'a>> + 'i, args: &Args) {
+    let mut iter: Box<dyn Iterator<Item = SearchResult<'a>> + 'i> = Box::new(results);
 
     if args.only_headers {
         iter = Box::new(
-            iter
-                .unique_by(|x| format!("{}:{}", x.file_path, x.headers.last().map_or(0, |x| x.line)))
-                .map(|x| { x.is_header = true; x })
+            iter.unique_by(|x| format!("{}:{}", x.file_path, x.headers.last().map_or(0, |x| x.line)))
+                .map(|mut x| {
+                    x.is_header = true;
+                    x
+                }),
         );
     }
 
     if let Some(count) = args.count {
-        iter = Box::new(
-            iter.take(count)
-        );
+        iter = Box::new(iter.take(count));
     }
 
     iter.for_each(|x| x.print());
-
-    Ok(())
 }
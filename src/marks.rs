@@ -1,24 +1,160 @@
+use chrono::{DateTime, Utc};
 use combine::Parser;
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
+use itertools::Itertools;
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::iter::Peekable;
-use walkdir::DirEntry;
-use walkdir::WalkDir;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use ignore::overrides::Override;
+use ignore::{DirEntry, WalkBuilder, WalkState};
 
-use crate::args::Args;
+use crate::args::{Args, Rank, SearchUnit};
+use crate::boost::{self, Signals, Weights};
+use crate::explain::{self, Explanation, SectionTrace, TermMatch};
 use crate::extensions::StartsWithIgnoreCase;
-use crate::org::datetime::OrgDateTime;
-use crate::org::header::OrgHeader;
+use crate::formats::{self, BlockTracker, DocumentFormat, SourceText};
+use crate::markup;
+use crate::matcher::{self, Matcher};
+use crate::org::block::OrgBlock;
+use crate::org::datetime::{OrgDatePlan, OrgDateTime};
+use crate::org::document::OrgDocument;
+use crate::org::drawer::{DrawerBuilder, OrgDrawer};
+use crate::org::footnote::OrgFootnote;
+use crate::org::header::{OrgHeader, PriorityRange};
+use crate::org::link::OrgLink;
+use crate::org::settings::{self, FileSettings};
+use crate::org::target::OrgTarget;
+use crate::org::table::{self, TableBuilder};
 use crate::parsers;
+use crate::prefilter::Prefilter;
+use crate::query::Query;
+use crate::rank::{self, CorpusStats, SectionStats};
 use crate::result::SearchResult;
 use crate::utils::file_utils;
 
+/// The file that is being searched, and the things about it that stay the same while its units
+/// are matched.
+struct FileContext<'f> {
+    file: &'f Path,
+    filename: &'f str,
+    source: SourceText,
+    doc_type: DocType,
+    /// Settings from the keywords of the file and its setup files.
+    settings: FileSettings,
+    /// Footnote definitions in the file, by their names. Only collected with --footnotes.
+    footnotes: HashMap<String, String>,
+    /// When was the file modified. Only read with the recency boost.
+    modified: Option<DateTime<Utc>>,
+    /// When the search started, for the boosts that depend on time.
+    now: DateTime<Utc>,
+}
+
+/// Name of the ignore files of marks, which are read along with .gitignore and .ignore files.
+pub const MARKS_IGNORE: &str = ".marksignore";
+
+/// Names of the ignore files that exclude files from the search, except for the global ones of
+/// git.
+pub const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", MARKS_IGNORE];
+
+/// How many entries may wait to be searched while the tree is being walked.
+const WALK_BUFFER: usize = 1024;
+
+/// The headers that the current line of the searched file is under, from the top level header to
+/// the closest one.
+#[derive(Default)]
+struct Hierarchy<'a> {
+    headers: Vec<OrgHeader<'a>>,
+    /// Contents of the headers without the inline markup, joined with ` / `. Matchers see it in
+    /// front of the text of every unit, so it's only built when the headers change.
+    breadcrumb: String,
+    /// Headers shared by the results under them, so that they are only copied once per section.
+    shared: Option<Arc<[OrgHeader<'a>]>>,
+}
+
+impl<'a> Hierarchy<'a> {
+    /// Add a header, replacing the headers that are not its parents. Depths may skip levels, like
+    /// `*` followed by `***`.
+    fn push(&mut self, header: OrgHeader<'a>, doc_type: &DocType) {
+        while self.headers.last().is_some_and(|x| x.depth >= header.depth) {
+            self.headers.pop();
+        }
+        self.headers.push(header);
+        self.breadcrumb = self
+            .headers
+            .iter()
+            .map(|x| markup::strip(&x.content, doc_type))
+            .join(" / ");
+        self.shared = None;
+    }
+
+    /// Add a drawer to the closest header, if there is one.
+    fn add_drawer(&mut self, drawer: OrgDrawer) {
+        if let Some(header) = self.headers.last_mut() {
+            header.add_drawer(drawer);
+            self.shared = None;
+        }
+    }
+
+    /// The headers, shared with the other results that were found under them.
+    fn shared(&mut self) -> Arc<[OrgHeader<'a>]> {
+        self.shared
+            .get_or_insert_with(|| self.headers.as_slice().into())
+            .clone()
+    }
+}
+
+impl<'a> Deref for Hierarchy<'a> {
+    type Target = [OrgHeader<'a>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.headers
+    }
+}
+
+/// Lines that are matched against the query together, see `--unit`.
+struct Unit<'t> {
+    /// Index of the first line.
+    start: usize,
+    /// Index of the last line.
+    end: usize,
+    /// Lines of the unit, borrowed from the text of the file.
+    lines: Vec<&'t str>,
+    /// Does the unit start with a header line?
+    is_header: bool,
+    /// Is the unit a whole block, see `--whole-blocks`?
+    is_block: bool,
+    /// Does any of the lines belong to a table row that matches the col: terms of the query?
+    has_matching_row: bool,
+}
+
+/// Results of searching a file.
+#[derive(Default)]
+pub struct FileMatches<'a> {
+    pub results: Vec<SearchResult<'a>>,
+    /// Statistics of all sections of the file, for ranking the results of all files. Only
+    /// collected with --rank bm25.
+    pub corpus: CorpusStats,
+    /// Whether each section of the file was searched or skipped. Only collected with
+    /// --explain-file.
+    pub trace: Vec<SectionTrace>,
+}
+
 pub struct Marks<'a> {
     pub args: &'a Args,
-    pub matcher: SkimMatcherV2,
+    /// Matcher of the fuzzy terms of the query, see `--matcher`.
+    pub matcher: Box<dyn Matcher>,
+    /// The query to search for. Empty if a subcommand is being run.
+    pub query: Query,
+    /// Formats of the files to search, see `--type`.
+    pub formats: Vec<Box<dyn DocumentFormat>>,
+    /// Weights of the boosts, see `--boost`.
+    pub weights: Weights,
+    /// Skips the files that can't match the query. Off with --search-filename, as the file name
+    /// is matched along with the text.
+    pub prefilter: Option<Prefilter>,
 }
 
 #[derive(Debug, Clone)]
@@ -29,22 +165,72 @@ pub enum DocType {
 
 impl<'a> Marks<'a> {
     pub fn new(args: &'a Args) -> Marks<'a> {
-        // TODO: parametrize this
-        let matcher = SkimMatcherV2::default();
+        let matcher = matcher::from_args(args);
+
+        let query = args.query.clone().unwrap_or_default();
+        let formats = formats::enabled(args);
+        let weights = Weights::from_args(args);
+        let prefilter = Prefilter::new(&query).filter(|_| !args.search_filename);
 
-        Marks { args, matcher }
+        Marks {
+            args,
+            matcher,
+            query,
+            formats,
+            weights,
+            prefilter,
+        }
+    }
+
+    /// Walk the tree in parallel, skipping the hidden, ignored and blacklisted entries. Entries
+    /// come in no particular order, as soon as they are found.
+    fn walk(&self) -> impl Iterator<Item = DirEntry> {
+        let is_ignoring = !self.args.no_ignore;
+        let blacklist = self.args.blacklist_folder.clone();
+        // Invalid globs are reported by Args::validate
+        let overrides = self.args.overrides().unwrap_or_else(|_| Override::empty());
+
+        let mut builder = WalkBuilder::new(&self.args.path);
+        builder
+            .hidden(!self.args.hidden)
+            .parents(is_ignoring)
+            .ignore(is_ignoring)
+            .git_ignore(is_ignoring)
+            .git_global(is_ignoring)
+            .git_exclude(is_ignoring)
+            // Notes are often not in a git repository, but their .gitignore files still count
+            .require_git(false)
+            .overrides(overrides)
+            .filter_entry(move |e| !is_blacklisted(&blacklist, e));
+        if is_ignoring {
+            builder.add_custom_ignore_filename(MARKS_IGNORE);
+        }
+        let walker = builder.build_parallel();
+
+        // The walk stops once the entries are not needed anymore
+        let (sender, receiver) = mpsc::sync_channel(WALK_BUFFER);
+        thread::spawn(move || {
+            walker.run(|| {
+                let sender = sender.clone();
+                Box::new(move |entry| match entry.map(|x| sender.send(x)) {
+                    Ok(Err(_)) => WalkState::Quit,
+                    _ => WalkState::Continue,
+                })
+            })
+        });
+        receiver.into_iter()
+    }
+
+    /// Find the directories that may contain files to search, including the root.
+    pub fn find_dirs(&'a self) -> impl Iterator<Item = DirEntry> + 'a {
+        self.walk().filter(|e| e.file_type().is_some_and(|x| x.is_dir()))
     }
 
     pub fn find_files(&'a self) -> impl Iterator<Item = DirEntry> + 'a {
-        WalkDir::new(&self.args.path)
-            .into_iter()
-            .filter_entry(move |e| !file_utils::is_hidden(e) && !self.is_file_blacklisted(e))
-            .filter_map(|e| e.ok())
+        self.walk()
             .filter_map(move |e| {
-                if e.file_type().is_file() {
-                    if (!self.args.no_org && self.is_org_file(&e))
-                        || (!self.args.no_markdown && self.is_md_file(&e))
-                    {
+                if e.file_type().is_some_and(|x| x.is_file()) {
+                    if self.format_of(e.path()).is_some() {
                         return Some(e);
                     }
                 }
@@ -54,48 +240,142 @@ impl<'a> Marks<'a> {
     }
 
     // TODO: refactor/divide into smaller functions
-    pub fn search_file(&self, file: &DirEntry) -> Option<Vec<SearchResult>> {
-        let filename = file.file_name().to_str()?;
-        let doc_type = self.get_doc_type(&file);
+    pub fn search_file(&self, file: &Path) -> Option<FileMatches<'a>> {
+        let filename = file.file_name()?.to_str()?;
+        let format = self.format_of(file)?;
+        let doc_type = format.doc_type();
 
-        let reader = BufReader::new(File::open(file.path()).ok()?);
+        let mut source = self.read_file(file, format)?;
+        if self.args.follow_includes && format.has_file_settings() {
+            source = settings::expand_includes(file, &source.text);
+        }
+        // Included files are a part of the text, so they are looked at as well
+        if self.prefilter.as_ref().is_some_and(|x| !x.may_match(&source.text)) {
+            return Some(FileMatches::default());
+        }
+        let settings = self.read_settings(file, format, &source);
+        // Definitions usually come after the references, so they are collected beforehand
+        let footnotes = if self.args.footnotes {
+            source
+                .text
+                .lines()
+                .flat_map(|x| OrgFootnote::parse_all(x, 0, &doc_type))
+                .filter_map(|x| Some((x.name, x.definition?)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let modified = if self.weights.recency != 0.0 {
+            let modified = std::fs::metadata(file).and_then(|x| x.modified());
+            modified.ok().map(DateTime::<Utc>::from)
+        } else {
+            None
+        };
+        let context = FileContext {
+            file,
+            filename,
+            source,
+            doc_type,
+            settings,
+            footnotes,
+            modified,
+            now: Utc::now(),
+        };
         let mut results = vec![];
 
-        let mut headers: Vec<OrgHeader> = vec![];
+        let mut headers = Hierarchy::default();
         let mut last_depth = 0;
         let mut skip_section = false;
+        // Do the headers in the hierarchy have the --tagged tags and the --prop properties?
+        let mut matches_hierarchy = false;
+        // Why the current section is skipped, only reported with --explain-file
+        let mut skip_reasons: Vec<String> = vec![];
+        let is_tracing = self.args.explain_file.is_some();
+        let mut trace = vec![];
+        let mut tables = TableBuilder::default();
+        let mut blocks = BlockTracker::default();
+        let mut drawers = DrawerBuilder::default();
+        let mut unit: Option<Unit> = None;
 
-        let mut iter = reader.lines().filter_map(|x| x.ok()).enumerate().peekable();
+        // Sections are ranked as a whole, their results get their statistics once they end
+        let is_ranked = self.args.rank == Rank::Bm25;
+        let terms = rank::query_terms(&self.query);
+        let mut corpus = CorpusStats::new(&terms);
+        let mut section = SectionStats::new(&terms);
+        let mut section_start = 0;
+
+        let mut iter = context.source.text.lines().enumerate().peekable();
         while let Some((index, line)) = iter.next() {
-            let header_info = self.parse_header(&mut iter, &doc_type, &line, index);
+            // Lines inside of blocks, like comments in code blocks, are never headers
+            let in_block = blocks.push(format, index, line, iter.clone().map(|(_, x)| x));
+            let header_info = if in_block {
+                None
+            } else {
+                self.parse_header(&mut iter, format, &context.settings, line, index)
+            };
             let is_header = header_info.is_some();
 
-            if let Some(header) = header_info {
-                let depth = header.depth;
+            if !self.query.cols.is_empty() {
+                tables.push(index, line, iter.peek().map(|(_, x)| *x));
+            }
 
-                if depth > last_depth {
-                    headers.push(header);
-                } else if last_depth == depth {
-                    let lastn = headers.len() - 1;
-                    headers[lastn] = header;
-                } else {
-                    headers.truncate(depth);
+            // With --whole-blocks, blocks are units on their own and the lines inside of them are
+            // never boundaries
+            let in_whole_block = self.args.whole_blocks && in_block;
+            let starts_block = in_whole_block && blocks.current().is_some_and(|x| x.line == index);
+
+            // Match the previous unit before the header hierarchy changes
+            let is_boundary = is_header
+                || starts_block
+                || (!in_whole_block
+                    && match self.args.unit {
+                        SearchUnit::Line => true,
+                        SearchUnit::Paragraph => line.trim().is_empty(),
+                        SearchUnit::Section => false,
+                    });
+            if is_boundary {
+                if let Some(unit) = unit.take() {
+                    results.extend(self.match_unit(unit, &mut headers, &context));
+                }
+            }
 
-                    // (depth - 1) will not work because header hiearchy may go like this:
-                    // * ***
-                    let curr_len = headers.len();
-                    headers[curr_len - 1] = header;
+            if is_ranked {
+                match &header_info {
+                    Some(header) => {
+                        let finished = std::mem::replace(&mut section, SectionStats::new(&terms));
+                        finish_section(&mut results[section_start..], finished, &mut corpus);
+                        section_start = results.len();
+                        let content = markup::strip(&header.content, &context.doc_type);
+                        section.add(&terms, &content, rank::HEADER_WEIGHT);
+                    }
+                    None => {
+                        let line = markup::strip(line, &context.doc_type);
+                        section.add(&terms, &line, 1.0);
+                    }
                 }
+            }
+
+            if let Some(header) = header_info {
+                let depth = header.depth;
+                let is_child = depth > last_depth;
+
+                // Depths may skip levels, like `*` followed by `***`, so pop every header that is
+                // not a parent of this one
+                headers.push(header, &context.doc_type);
                 last_depth = depth;
 
                 // Check if any of the headers in the hierarchy contains the given tags
-                // or the given props. Skip the check if we already found match in any of the parent headers.
-                if !(!skip_section && depth > last_depth) {
+                // or the given props. Skip the check if the parent header already matched them.
+                skip_reasons.clear();
+                if !(is_child && matches_hierarchy) {
                     let matches_tags = self
                         .args
                         .tagged
                         .iter()
-                        .all(|x| headers.iter().any(|header| header.tags.contains(x)));
+                        .all(|x| {
+                            context.settings.filetags.contains(x)
+                                || headers.iter().any(|header| header.tags.contains(x))
+                        });
 
                     let matches_props = self.args.prop.iter().all(|(key, val)| {
                         headers.iter().any(|header| {
@@ -107,11 +387,19 @@ impl<'a> Marks<'a> {
                         })
                     });
 
-                    skip_section = !(matches_tags && matches_props)
+                    matches_hierarchy = matches_tags && matches_props;
+                    if !matches_tags {
+                        skip_reasons.push(format!("not tagged with {}", self.args.tagged.join(", ")));
+                    }
+                    if !matches_props {
+                        skip_reasons.push("doesn't have the --prop properties".to_string());
+                    }
                 }
+                skip_section = !matches_hierarchy;
 
                 if !skip_section {
                     let curr_header = headers.last().unwrap();
+                    let priorities = self.priority_range(&context.settings);
                     if !self.args.todo.is_empty() {
                         let has_todo = self
                             .args
@@ -119,6 +407,9 @@ impl<'a> Marks<'a> {
                             .iter()
                             .any(|x| curr_header.todo.as_ref().map_or(false, |y| y == x));
                         skip_section = skip_section || !has_todo;
+                        if !has_todo {
+                            skip_reasons.push("TODO state is not one of --todo".to_string());
+                        }
                     }
 
                     if !self.args.priority.is_empty() {
@@ -129,32 +420,64 @@ impl<'a> Marks<'a> {
                             .any(|x| curr_header.priority.as_ref().map_or(false, |y| x == y));
 
                         skip_section = skip_section || !is_right_priority;
+                        if !is_right_priority {
+                            skip_reasons.push("priority is not one of --priority".to_string());
+                        }
                     }
 
                     if let Some(priority) = &self.args.priority_lt {
                         let is_lt_than = curr_header
                             .priority
                             .as_ref()
-                            .map_or(false, |x| x < priority);
+                            .is_some_and(|x| priorities.compare(x, priority) == Some(Ordering::Less));
                         skip_section = skip_section || !is_lt_than;
+                        if !is_lt_than {
+                            skip_reasons.push(format!("priority is not lower than {}", priority));
+                        }
                     }
 
                     if let Some(priority) = &self.args.priority_gt {
                         let is_gt_than = curr_header
                             .priority
                             .as_ref()
-                            .map_or(false, |x| x > priority);
+                            .is_some_and(|x| priorities.compare(x, priority) == Some(Ordering::Greater));
                         skip_section = skip_section || !is_gt_than;
+                        if !is_gt_than {
+                            skip_reasons.push(format!("priority is not higher than {}", priority));
+                        }
                     }
 
                     if let Some(schedule) = &self.args.scheduled_at {
-                        //println!("{:?}", curr_header.datetime);
-                        skip_section = skip_section || !curr_header
+                        let is_scheduled = curr_header
                             .datetime
                             .as_ref()
-                            .map_or(false, |datetime| datetime.compare_with(schedule, PartialEq::eq, PartialEq::eq));
+                            .is_some_and(|datetime| datetime.compare_with(schedule, PartialEq::eq, PartialEq::eq));
+                        skip_section = skip_section || !is_scheduled;
+                        if !is_scheduled {
+                            skip_reasons.push("not scheduled at --scheduled-at".to_string());
+                        }
                     }
                 }
+
+                // Commented, archived and noexport subtrees are inactive, along with all of their children
+                if let Some(reason) = headers.iter().find_map(|x| self.inactive_reason(x)) {
+                    skip_section = true;
+                    skip_reasons.push(reason);
+                }
+            }
+
+            if is_header {
+                drawers.reset();
+            }
+            let drawer = if is_header || in_block || !format.has_drawers() {
+                None
+            } else {
+                let rest = iter.clone().map(|(_, x)| x);
+                let rest = rest.take_while(|x| format.parse_header(x, None).is_none());
+                drawers.push(index, line, rest).map(str::to_string)
+            };
+            if let Some(drawer) = drawers.take_finished() {
+                headers.add_drawer(drawer);
             }
 
             // Skip 0-level if are looking for props or tags
@@ -170,149 +493,559 @@ impl<'a> Marks<'a> {
                     || self.args.scheduled_at.is_some())
             {
                 skip_section = true;
+                if skip_reasons.is_empty() {
+                    skip_reasons.push("before the first header, the filters need a header".to_string());
+                }
+            }
+
+            if is_tracing && (is_header || index == 0) {
+                trace.push(SectionTrace {
+                    line: index + 1,
+                    header: headers.last().filter(|_| is_header).map(|x| x.content.clone()),
+                    reasons: skip_reasons.clone(),
+                });
             }
 
-            if skip_section {
+            if skip_section
+                || !self.is_in_drawer_scope(drawer.as_deref())
+                || !self.is_in_block_scope(blocks.current())
+                || (self.args.unit == SearchUnit::Paragraph
+                    && line.trim().is_empty()
+                    && !in_whole_block)
+            {
                 continue;
             }
 
-            // TODO: Maybe don't do this every loop?
-            let full: String = {
-                let mut result = headers
-                    .iter()
-                    .map(|x| x.content.to_owned())
-                    .collect::<Vec<_>>()
-                    .join(" / ");
+            // Only the rows of tables with a header can match, header itself is not a row
+            let is_matching_row = !self.query.cols.is_empty()
+                && tables
+                    .current()
+                    .and_then(|x| {
+                        let row = x.rows.last().filter(|row| row.line == index)?;
+                        Some((x.header.as_ref()?, row))
+                    })
+                    .is_some_and(|(header, row)| {
+                        self.matches_columns(&self.query, header, &row.cells)
+                    });
+
+            let current = unit.get_or_insert_with(|| Unit {
+                start: index,
+                end: index,
+                lines: vec![],
+                is_header,
+                is_block: in_whole_block,
+                has_matching_row: false,
+            });
+            current.end = index;
+            current.lines.push(line);
+            current.has_matching_row |= is_matching_row;
 
-                if !is_header {
-                    result.push_str(&line);
+            if in_whole_block && blocks.is_finished() {
+                if let Some(unit) = unit.take() {
+                    results.extend(self.match_unit(unit, &mut headers, &context));
                 }
+            }
 
-                if self.args.search_filename {
-                    result.push_str(&filename);
+            // Headers are paragraphs on their own
+            if is_header && self.args.unit == SearchUnit::Paragraph {
+                if let Some(unit) = unit.take() {
+                    results.extend(self.match_unit(unit, &mut headers, &context));
                 }
+            }
+        }
 
-                result
-            };
+        if let Some(unit) = unit.take() {
+            results.extend(self.match_unit(unit, &mut headers, &context));
+        }
+        if is_ranked {
+            finish_section(&mut results[section_start..], section, &mut corpus);
+        }
 
-            // Check regexes
-            if !self.args.query.regexes.iter().all(|x| x.is_match(&full))
-                || !self.args.query.musts.iter().all(|x| full.contains(x))
-                || self.args.query.nones.iter().any(|x| full.contains(x))
-            {
-                continue;
+        Some(FileMatches {
+            results,
+            corpus,
+            trace,
+        })
+    }
+
+    /// Match the given unit, which belongs to the given header hierarchy, against the query.
+    fn match_unit(
+        &self,
+        unit: Unit,
+        headers: &mut Hierarchy<'a>,
+        context: &FileContext,
+    ) -> Option<SearchResult<'a>> {
+        let doc_type = &context.doc_type;
+        // Header lines are already a part of the hierarchy
+        let body = unit.lines.iter().skip(usize::from(unit.is_header));
+
+        // Matchers see the text without the inline markup, output still shows the original line
+        let full: String = {
+            let mut result = headers.breadcrumb.clone();
+
+            // Keep the last header and the body apart, for the matchers that look at whole words
+            let body_text = body.clone().map(|x| markup::strip(x, doc_type)).join(" ");
+            if !result.is_empty() && !body_text.is_empty() {
+                result.push_str(" / ");
+            }
+            result.push_str(&body_text);
+
+            if self.args.search_filename {
+                result.push_str(context.filename);
             }
 
-            // Fuzzy match
-            let points = self
-                .args
+            result
+        };
+
+        if !self.query.links.is_empty() {
+            let links = body
+                .clone()
+                .flat_map(|x| OrgLink::parse_all(x, unit.start, doc_type))
+                .collect::<Vec<_>>();
+            let has_links = self
                 .query
-                .rest
+                .links
                 .iter()
-                .filter_map(|q| self.matcher.fuzzy_match(&full, &q))
-                .collect::<Vec<_>>();
-            if points.len() > 0 || self.args.query.rest.len() == 0 {
-                results.push(SearchResult {
-                    line: index + 1,
-                    file_path: file.path().to_str()?.to_string(),
-                    score: points.iter().sum::<i64>(),
-                    headers: headers.clone(),
-                    content: line,
-                    args: self.args,
-                    is_header,
-                });
+                .all(|x| links.iter().any(|link| link.raw.contains(x.as_str())));
+            if !has_links {
+                return None;
             }
         }
 
-        return Some(results);
-    }
+        if !self.query.cols.is_empty() && !unit.has_matching_row {
+            return None;
+        }
+
+        let score = self.match_query(&self.query, &full)?;
 
-    fn is_file_blacklisted(&'a self, entry: &DirEntry) -> bool {
-        entry
-            .file_name()
-            .to_str()
-            .map(|s| self.args.blacklist_folder.contains(&s.to_string()))
-            .unwrap_or(false)
+        // Show the definitions of the footnotes that are referenced in the unit
+        let footnotes = if context.footnotes.is_empty() {
+            vec![]
+        } else {
+            body.flat_map(|x| OrgFootnote::parse_all(x, unit.start, doc_type))
+                .filter(|x| x.is_reference && x.definition.is_none())
+                .filter_map(|x| {
+                    let definition = context.footnotes.get(&x.name)?.clone();
+                    Some((x.name, definition))
+                })
+                .unique()
+                .collect()
+        };
+
+        let content = match self.args.unit {
+            _ if unit.is_block => unit.lines.join("\n"),
+            SearchUnit::Paragraph => unit.lines.join(" "),
+            _ => unit.lines[0].to_string(),
+        };
+
+        // Included lines are reported in the file they come from
+        let (path, line) = context.source.origin(unit.start);
+        let (cell, line) = context.source.locate(line);
+        Some(SearchResult {
+            line: line + 1,
+            cell: cell.map(|x| x + 1),
+            span: unit.end - unit.start + 1,
+            file_path: path.unwrap_or(context.file).to_str()?.to_string(),
+            footnotes,
+            score,
+            headers: headers.shared(),
+            content,
+            args: self.args,
+            is_header: unit.is_header,
+            section: None,
+            base_score: score,
+            signals: self.signals(headers, context),
+            explanation: self.args.is_explaining().then(|| self.explain_match(&full)),
+        })
     }
 
-    fn is_org_file(&'a self, e: &DirEntry) -> bool {
-        match e.path().extension().map(|x| x.to_str()).flatten() {
-            Some(x) => self.args.org_extension.iter().any(|y| x == y),
-            None => false,
+    /// Explain how the query matches the text of a unit, see [`Marks::match_query`].
+    fn explain_match(&self, full: &str) -> Explanation {
+        let term = |kind, term: &String, score, spans| TermMatch {
+            kind,
+            term: term.clone(),
+            score,
+            spans,
+        };
+
+        let mut terms = vec![];
+        for x in &self.query.rest {
+            let (score, indices) = self.matcher.indices(full, x).unzip();
+            let spans = indices.map_or(vec![], |x| explain::spans(&x));
+            terms.push(term("fuzzy", x, score, spans));
+        }
+        for x in &self.query.musts {
+            terms.push(term("must", x, None, explain::literal_spans(x, full)));
+        }
+        for x in &self.query.regexes {
+            let spans = explain::regex_spans(x, full);
+            terms.push(term("regex", &x.as_str().to_string(), None, spans));
+        }
+        for x in &self.query.nones {
+            terms.push(term("none", x, None, vec![]));
+        }
+
+        Explanation {
+            text: full.to_string(),
+            terms,
+            filters: explain::filters(self.args, &self.query),
+            bm25: vec![],
         }
     }
 
-    fn is_md_file(&'a self, e: &DirEntry) -> bool {
-        match e.path().extension().map(|x| x.to_str()).flatten() {
-            Some(x) => self.args.md_extension.iter().any(|y| x == y),
-            None => false,
+    /// Signals of a result under the given header hierarchy, for boosting its score.
+    fn signals(&self, headers: &[OrgHeader], context: &FileContext) -> Signals {
+        let Some(header) = headers.last() else {
+            return Signals {
+                depth: 1.0,
+                recency: context.modified.map(|x| boost::recency(x, context.now)),
+                ..Default::default()
+            };
+        };
+
+        // Terms that match the header on their own, out of all the fuzzy and must terms
+        let content = markup::strip(&header.content, &context.doc_type);
+        let terms = self.query.rest.len() + self.query.musts.len();
+        let matching = self
+            .query
+            .rest
+            .iter()
+            .filter(|x| self.matcher.score(&content, x).is_some())
+            .count()
+            + self.query.musts.iter().filter(|x| content.contains(x.as_str())).count();
+
+        let created = headers.iter().rev().find_map(|x| boost::created(&x.properties));
+        let deadline = header
+            .datetime
+            .as_ref()
+            .filter(|x| x.date_plan == OrgDatePlan::Deadline);
+
+        Signals {
+            header: if terms == 0 { 0.0 } else { matching as f64 / terms as f64 },
+            depth: boost::depth(header.depth),
+            recency: created
+                .or(context.modified)
+                .map(|x| boost::recency(x, context.now)),
+            priority: header
+                .priority
+                .as_ref()
+                .and_then(|x| self.priority_range(&context.settings).importance(x)),
+            deadline: deadline.map(|x| boost::deadline(x.date_start, context.now)),
         }
     }
 
-    fn get_doc_type(&self, file: &DirEntry) -> DocType {
-        if self.is_md_file(file) {
-            return DocType::Markdown;
+    /// Why the subtree of the given header is excluded from the search, if it is. COMMENT headers
+    /// and headers tagged with ARCHIVE or noexport are excluded unless they're explicitly included.
+    fn inactive_reason(&self, header: &OrgHeader) -> Option<String> {
+        let has_tag = |tag: &str| header.tags.iter().any(|x| x == tag);
+
+        let reason = if header.is_comment && !self.args.include_comments {
+            "COMMENT"
+        } else if has_tag("ARCHIVE") && !self.args.include_archived {
+            "ARCHIVE"
+        } else if has_tag("noexport") && !self.args.include_noexport {
+            "noexport"
         } else {
-            return DocType::OrgMode;
+            return None;
+        };
+        Some(format!("in the {} subtree `{}`", reason, header.content))
+    }
+
+    /// The priorities of the file, from its `#+PRIORITIES:`, its cookies or `--priorities`.
+    fn priority_range<'s>(&'s self, settings: &'s FileSettings) -> &'s PriorityRange {
+        settings
+            .priorities
+            .as_ref()
+            .unwrap_or(&self.args.priorities)
+    }
+
+    /// Should a line in the given drawer, or outside of any drawer, be searched? Drawers are
+    /// searched unless --no-drawers is given, drawer:NAME terms limit the search to the drawers.
+    fn is_in_drawer_scope(&self, drawer: Option<&str>) -> bool {
+        match drawer {
+            Some(name) => {
+                !self.args.no_drawers
+                    && (self.query.drawers.is_empty()
+                        || self.query.drawers.iter().any(|x| x.eq_ignore_ascii_case(name)))
+            }
+            None => self.query.drawers.is_empty(),
         }
     }
 
-    fn parse_header<I>(
-        &self,
-        iter: &mut Peekable<I>,
-        typ: &DocType,
-        line: &str,
-        idx: usize,
-    ) -> Option<OrgHeader>
-    where
-        I: Iterator<Item = (usize, String)>,
-    {
-        let x = match typ {
-            DocType::Markdown => '#',
-            DocType::OrgMode => '*',
+    /// Should a line in the given block, or outside of any block, be searched? Lines are only
+    /// searched in the blocks that match the block terms of the query, like lang:python.
+    fn is_in_block_scope(&self, block: Option<&OrgBlock>) -> bool {
+        if !self.query.has_block_terms() {
+            return true;
+        }
+
+        block.is_some_and(|x| self.matches_block(&self.query, x))
+    }
+
+    /// Check if the block matches the lang:, tangle:, block: and arg: terms of the query.
+    pub fn matches_block(&self, query: &Query, block: &OrgBlock) -> bool {
+        let has_lang = |lang: &String| {
+            block
+                .lang
+                .as_ref()
+                .is_some_and(|x| x.eq_ignore_ascii_case(lang))
         };
+        // `:tangle no` is the same as not tangling at all
+        let has_tangle = |file: &String| {
+            block
+                .arg("tangle")
+                .is_some_and(|x| x != "no" && x.contains(file.as_str()))
+        };
+        let has_name = |name: &String| block.name.as_ref().is_some_and(|x| x == name);
+        let has_arg = |(key, value): &(String, Option<String>)| match (block.arg(key), value) {
+            (Some(arg), Some(value)) => arg.contains(value.as_str()),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        query.langs.iter().all(has_lang)
+            && query.tangles.iter().all(has_tangle)
+            && query.blocks.iter().all(has_name)
+            && query.block_args.iter().all(has_arg)
+    }
 
-        let mut chars = line.chars().into_iter();
-        if chars.next() != Some(x) {
+    /// Check `full` against the regexes, must and none terms of the query and fuzzy match the
+    /// rest. Returns the sum of the fuzzy match scores, or `None` if `full` does not match.
+    pub fn match_query(&self, query: &Query, full: &str) -> Option<i64> {
+        if !query.regexes.iter().all(|x| x.is_match(full))
+            || !query.musts.iter().all(|x| full.contains(x))
+            || query.nones.iter().any(|x| full.contains(x))
+        {
             return None;
         }
 
-        let mut depth: usize = 1;
-        for chr in &mut chars {
-            if chr == x {
-                depth += 1;
-                continue;
-            } else if chr == ' ' {
-                break;
+        let points = query
+            .rest
+            .iter()
+            .filter_map(|q| self.matcher.score(full, q))
+            .collect::<Vec<_>>();
+
+        if !points.is_empty() || query.rest.is_empty() {
+            Some(points.iter().sum())
+        } else {
+            None
+        }
+    }
+
+    /// Check if the cells of a table row match the col:Name=value terms of the query.
+    pub fn matches_columns(&self, query: &Query, header: &[String], cells: &[String]) -> bool {
+        query.cols.iter().all(|(name, value)| {
+            let cell = table::column(header, name).and_then(|x| cells.get(x));
+            match (cell, value) {
+                (Some(cell), Some(value)) => cell.eq_ignore_ascii_case(value),
+                (Some(cell), None) => !cell.is_empty(),
+                (None, _) => false,
+            }
+        })
+    }
+
+    /// Read the whole file and extract the text to search from it, see [`Marks::extract_source`].
+    fn read_file(&self, file: &Path, format: &dyn DocumentFormat) -> Option<SourceText> {
+        let bytes = std::fs::read(file).ok()?;
+        self.extract_source(file, format, &bytes)
+    }
+
+    /// Decode the contents of the file, then extract the text to search from it. Files that can't
+    /// be decoded cleanly are still searched, but a warning is printed for them.
+    pub fn extract_source(
+        &self,
+        file: &Path,
+        format: &dyn DocumentFormat,
+        bytes: &[u8],
+    ) -> Option<SourceText> {
+        let (text, warning) = file_utils::decode(bytes);
+        if let Some(warning) = warning {
+            eprintln!("warning: {}: {}", file.display(), warning);
+        }
+
+        match format.extract(text) {
+            Ok(source) => Some(source),
+            Err(err) => {
+                eprintln!("warning: {}: {}", file.display(), err);
+                None
+            }
+        }
+    }
+
+    /// Collect the settings of the file, like its TODO keywords, if its format has any.
+    pub fn read_settings(
+        &self,
+        file: &Path,
+        format: &dyn DocumentFormat,
+        source: &SourceText,
+    ) -> FileSettings {
+        if !format.has_file_settings() {
+            return FileSettings::default();
+        }
+
+        let mut settings = FileSettings::collect(file, &source.text);
+        if settings.priorities.is_none() {
+            settings.priorities = self.cookie_range(format, &source.text);
+        }
+        settings
+    }
+
+    /// The range of the priority cookies of the file that are out of --priorities, if there are
+    /// any.
+    /// Headers without a cookie have no priority then, instead of the default one.
+    fn cookie_range(&self, format: &dyn DocumentFormat, text: &str) -> Option<PriorityRange> {
+        let cookies = text
+            .lines()
+            .filter_map(|x| format.parse_header(x, None))
+            .filter_map(|x| format.parse_todo(&format.parse_tags(&x.text).1).1)
+            .filter(|x| !self.args.priorities.contains(x))
+            .collect::<Vec<_>>();
+        PriorityRange::spanning(&cookies)
+    }
+
+    /// Parse the whole file into an [`OrgDocument`], collecting all of its headers and links.
+    pub fn parse_document(&self, file: &Path) -> Option<OrgDocument<'a>> {
+        let format = self.format_of(file)?;
+        let source = self.read_file(file, format)?;
+        let settings = self.read_settings(file, format, &source);
+        Some(self.parse_source(file, format, &source, &settings))
+    }
+
+    /// Parse the already read contents of the file, see [`Marks::parse_document`].
+    pub fn parse_source(
+        &self,
+        file: &Path,
+        format: &dyn DocumentFormat,
+        source: &SourceText,
+        settings: &FileSettings,
+    ) -> OrgDocument<'a> {
+        let doc_type = format.doc_type();
+
+        let mut headers = vec![];
+        let mut links = vec![];
+        let mut footnotes = vec![];
+        let mut targets = vec![];
+        let mut code_blocks = vec![];
+        let mut tables = TableBuilder::default();
+        let mut blocks = BlockTracker::default();
+        let mut drawers = DrawerBuilder::default();
+
+        let mut iter = source.text.lines().enumerate().peekable();
+        while let Some((index, line)) = iter.next() {
+            let in_block = blocks.push(format, index, line, iter.clone().map(|(_, x)| x));
+            code_blocks.extend(blocks.take_finished());
+            let header = if in_block {
+                None
             } else {
-                return None;
+                self.parse_header(&mut iter, format, settings, line, index)
+            };
+
+            if let Some(header) = header {
+                drawers.reset();
+                headers.push(header);
+            } else if !in_block && format.has_drawers() {
+                let rest = iter.clone().map(|(_, x)| x);
+                let rest = rest.take_while(|x| format.parse_header(x, None).is_none());
+                drawers.push(index, line, rest);
+                if let (Some(drawer), Some(header)) = (drawers.take_finished(), headers.last_mut()) {
+                    header.add_drawer(drawer);
+                }
             }
+
+            tables.push(index, line, iter.peek().map(|(_, x)| *x));
+            // Links in blocks are code, like `print("[[x.org]]")`, not links of the document
+            if in_block {
+                continue;
+            }
+            links.extend(OrgLink::parse_all(line, index, &doc_type));
+            footnotes.extend(OrgFootnote::parse_all(line, index, &doc_type));
+            if let DocType::OrgMode = doc_type {
+                targets.extend(OrgTarget::parse_all(line, index));
+            }
+        }
+        tables.finish();
+
+        OrgDocument {
+            path: file.to_path_buf(),
+            doc_type,
+            headers,
+            links,
+            footnotes,
+            targets,
+            blocks: code_blocks,
+            tables: tables.tables,
+        }
+    }
+
+    /// Find the format of the file at the given path, among the formats that are searched.
+    pub fn format_of(&self, path: &Path) -> Option<&dyn DocumentFormat> {
+        self.formats
+            .iter()
+            .find(|x| x.matches(path))
+            .map(|x| x.as_ref())
+    }
+
+    /// Parse the line into a header, consuming the lines after it that belong to the header, like
+    /// its planning line and its properties.
+    pub fn parse_header<'t, I>(
+        &self,
+        iter: &mut Peekable<I>,
+        format: &dyn DocumentFormat,
+        settings: &FileSettings,
+        line: &str,
+        idx: usize,
+    ) -> Option<OrgHeader<'a>>
+    where
+        I: Iterator<Item = (usize, &'t str)>,
+    {
+        let header = format.parse_header(line, iter.peek().map(|(_, x)| *x))?;
+        if header.is_underlined {
+            iter.next();
         }
 
         // TODO: it might be good if user does not search for these, simply don't parse them
         //       ex. if --prop does not exist in args, simply skip parse_org_props() call etc.
-        let (tags, rest) = self.parse_org_tags(&mut chars);
-        let ((todo, priority), content) = parsers::org_todo().parse(rest.as_str()).ok()?;
+        let (tags, rest) = format.parse_tags(&header.text);
+        let (todo, priority, rest) = format.parse_todo(&rest);
+        let (todo, rest) = settings.check_todo(todo, rest);
+        // Only formats with file settings have priority ranges, and so a default priority
+        let priority = priority.or_else(|| {
+            format
+                .has_file_settings()
+                .then(|| self.priority_range(settings).default.clone())
+                .flatten()
+        });
+        let (is_comment, content) = format.parse_comment(&rest);
         // FIXME: properties may come after datetime or vice versa. Not really sure tho
-        let datetime = self.parse_org_date_time(iter);
-        let properties = self.parse_org_props(iter);
+        let datetime = if format.has_planning() {
+            self.parse_org_date_time(iter)
+        } else {
+            None
+        };
+        let properties = if format.has_drawers() {
+            self.parse_org_props(iter)
+        } else {
+            HashMap::new()
+        };
 
         Some(OrgHeader {
-            depth,
+            depth: header.depth,
             content: content.into(),
             properties,
             tags,
+            drawers: vec![],
             datetime,
             line: idx,
             args: self.args,
             todo,
             priority,
+            is_comment,
         })
     }
 
-    fn parse_org_date_time<I>(&self, iter: &mut Peekable<I>) -> Option<OrgDateTime>
+    fn parse_org_date_time<'t, I>(&self, iter: &mut Peekable<I>) -> Option<OrgDateTime>
     where
-        I: Iterator<Item = (usize, String)>,
+        I: Iterator<Item = (usize, &'t str)>,
     {
         // Only ISO 8601 dates are supported
         // TODO: handle plain timestamps after headers
@@ -324,38 +1057,16 @@ impl<'a> Marks<'a> {
         if has_schedule {
             let (_, line_date) = iter.next().unwrap();
             let result: Result<(OrgDateTime, &str), _> =
-                parsers::org_date_time().parse(line_date.as_str());
+                parsers::org_date_time().parse(line_date);
             result.ok().map(|x| x.0)
         } else {
             None
         }
     }
 
-    /// Parse the tags from given line and return the tags along with the header that is stripped from the tags and whitespace.
-    fn parse_org_tags<I>(&self, chars: &mut I) -> (Vec<String>, String)
-    where
-        I: DoubleEndedIterator<Item = char>,
-    {
-        let mut rev_chars = chars.rev().peekable();
-        let has_tags = rev_chars.peek().map(|x| *x == ':').unwrap_or(false);
-        let rev_header = rev_chars.collect::<String>();
-
-        if has_tags {
-            let result: Result<(Vec<String>, &str), _> =
-                parsers::org_tags().parse(rev_header.as_str());
-            if let Ok((tags, rest)) = result {
-                (tags, rest.chars().rev().collect::<String>())
-            } else {
-                (vec![], rev_header.chars().rev().collect())
-            }
-        } else {
-            (vec![], rev_header.chars().rev().collect())
-        }
-    }
-
-    fn parse_org_props<I>(&self, iter: &mut Peekable<I>) -> HashMap<String, String>
+    fn parse_org_props<'t, I>(&self, iter: &mut Peekable<I>) -> HashMap<String, String>
     where
-        I: Iterator<Item = (usize, String)>,
+        I: Iterator<Item = (usize, &'t str)>,
     {
         let has_props = iter
             .peek()
@@ -371,7 +1082,7 @@ impl<'a> Marks<'a> {
                     return props;
                 } else {
                     let result: Result<((String, String), &str), _> =
-                        parsers::org_property().parse(&prop);
+                        parsers::org_property().parse(prop);
                     if let Ok(((key, val), _)) = result {
                         props.insert(key, val);
                     } else {
@@ -387,3 +1098,135 @@ impl<'a> Marks<'a> {
         props
     }
 }
+
+/// Is the name of the entry one of the --blacklist-folder names?
+fn is_blacklisted(blacklist: &[String], entry: &DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .map(|s| blacklist.contains(&s.to_string()))
+        .unwrap_or(false)
+}
+
+/// Give the results of a section that has ended the statistics of the section, and count the
+/// section in the corpus.
+fn finish_section(results: &mut [SearchResult], section: SectionStats, corpus: &mut CorpusStats) {
+    corpus.add(&section);
+    for result in results {
+        result.section = Some(section.clone());
+    }
+}
+
+#[test]
+fn test_parse_source_blocks() {
+    let args = Args::parse_from(["marks", "query", "."]);
+    let app = Marks::new(&args);
+    let parse = |name: &str, text: &str| {
+        let path = Path::new(name);
+        let format = app.format_of(path).unwrap();
+        let source = format.extract(text.to_string()).unwrap();
+        app.parse_source(path, format, &source, &FileSettings::default())
+    };
+
+    let doc = parse(
+        "a.org",
+        "* Code\n[[real.org]] [fn:1]\n#+begin_src python\nprint(\"[[nope.org]] [fn:2]\")\n#+end_src\n",
+    );
+    assert_eq!(doc.links.iter().map(|x| x.line).collect::<Vec<_>>(), vec![1]);
+    assert_eq!(doc.footnotes.iter().map(|x| x.line).collect::<Vec<_>>(), vec![1]);
+
+    let doc = parse("c.md", "# Code\n[real](real.md)\n```\n[code](notreal.md)\n```\n");
+    assert_eq!(doc.links.iter().map(|x| x.line).collect::<Vec<_>>(), vec![1]);
+
+    // A block that is never ended is just text, the headers after it are still headers
+    let doc = parse(
+        "a.org",
+        "* One\n#+begin_quote\nquoted\n* Two\nfoo here\n* Three\nbar here",
+    );
+    let headers = doc.headers.iter().map(|x| x.content.as_str()).collect::<Vec<_>>();
+    assert_eq!(headers, vec!["One", "Two", "Three"]);
+    assert!(doc.blocks.is_empty());
+}
+
+#[test]
+fn test_find_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let files = [
+        "a.org",
+        "b.md",
+        "c.txt",
+        ".hidden/d.org",
+        "built/e.org",
+        "private.org",
+        "archive/f.org",
+        "sub/Notes.org",
+    ];
+    for file in files {
+        let path = dir.path().join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "* Header\n").unwrap();
+    }
+    std::fs::write(dir.path().join(".gitignore"), "built/\n").unwrap();
+    std::fs::write(dir.path().join(MARKS_IGNORE), "private.org\n").unwrap();
+
+    let root = dir.path().to_str().unwrap();
+    let find = |options: &[&str]| {
+        let args = Args::parse_from(["marks", "x", root].iter().chain(options));
+        let app = Marks::new(&args);
+        let mut found = app
+            .find_files()
+            .map(|x| {
+                let path = x.path().strip_prefix(root).unwrap();
+                path.to_string_lossy().replace('\\', "/")
+            })
+            .collect::<Vec<_>>();
+        found.sort();
+        found
+    };
+
+    assert_eq!(find(&[]), vec!["a.org", "archive/f.org", "b.md", "sub/Notes.org"]);
+    assert_eq!(
+        find(&["--hidden"]),
+        vec![".hidden/d.org", "a.org", "archive/f.org", "b.md", "sub/Notes.org"]
+    );
+    assert_eq!(
+        find(&["--no-ignore"]),
+        vec!["a.org", "archive/f.org", "b.md", "built/e.org", "private.org", "sub/Notes.org"]
+    );
+    assert_eq!(find(&["--blacklist-folder", "archive"]), vec!["a.org", "b.md", "sub/Notes.org"]);
+    assert_eq!(find(&["--glob", "*.md"]), vec!["b.md"]);
+    assert_eq!(find(&["--glob", "!sub/**"]), vec!["a.org", "archive/f.org", "b.md"]);
+    assert_eq!(find(&["--glob", "**/notes.org"]), Vec::<String>::new());
+    assert_eq!(find(&["--iglob", "**/notes.org"]), vec!["sub/Notes.org"]);
+}
+
+#[test]
+fn test_numeric_priorities() {
+    use crate::org::header::OrgPriority;
+
+    let args = Args::parse_from(["marks", "x", "."]);
+    let app = Marks::new(&args);
+    let path = Path::new("a.org");
+    let format = app.format_of(path).unwrap();
+    let parse = |text: &str| {
+        let source = format.extract(text.to_string()).unwrap();
+        let settings = app.read_settings(path, format, &source);
+        let doc = app.parse_source(path, format, &source, &settings);
+        let range = app.priority_range(&settings).clone();
+        let priorities = doc.headers.into_iter().map(|x| x.priority).collect::<Vec<_>>();
+        (range, priorities)
+    };
+    let priority = |x: &str| Some(OrgPriority(x.into()));
+
+    // Numeric cookies don't fit the default range, so headers without one have no priority
+    let (range, priorities) = parse("* [#1] one\n* [#3] three\n* plain\n");
+    assert_eq!(priorities, vec![priority("1"), priority("3"), None]);
+    assert_eq!(range.importance(&OrgPriority("1".into())), Some(1.0));
+    assert_eq!(range.importance(&OrgPriority("3".into())), Some(0.0));
+
+    let (_, priorities) = parse("* [#A] one\n* plain\n");
+    assert_eq!(priorities, vec![priority("A"), priority("B")]);
+
+    let (_, priorities) = parse("#+PRIORITIES: 1 5 3\n* [#1] one\n* plain\n");
+    assert_eq!(priorities, vec![priority("1"), priority("3")]);
+}
@@ -0,0 +1,186 @@
+use combine::Parser;
+use itertools::Itertools;
+
+use crate::marks::DocType;
+use crate::parsers;
+
+/// Remove inline markup, like `*bold*`, `=code=` or `[[target][description]]`, from the given
+/// text so that it can be matched the way it reads. Links are replaced with their descriptions,
+/// or with their targets if they don't have one. Markup never spans lines.
+pub fn strip(text: &str, typ: &DocType) -> String {
+    if text.contains('\n') {
+        return text.split('\n').map(|x| strip(x, typ)).join("\n");
+    }
+
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(text.len());
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let chr = chars[idx];
+
+        if chr == '[' || (chr == '!' && chars.get(idx + 1) == Some(&'[')) {
+            if let Some((shown, len)) = parse_link(&chars[idx..], typ) {
+                result.push_str(&strip(&shown, typ));
+                idx += len;
+                continue;
+            }
+        }
+
+        if is_marker(chr, typ) && (idx == 0 || is_opening_boundary(chars[idx - 1])) {
+            if let Some((inner, len)) = parse_emphasis(&chars[idx..]) {
+                if is_verbatim(chr) {
+                    result.push_str(&inner);
+                } else {
+                    result.push_str(&strip(&inner, typ));
+                }
+                idx += len;
+                continue;
+            }
+        }
+
+        result.push(chr);
+        idx += 1;
+    }
+
+    result
+}
+
+/// Split the text into lowercased words, made of letters and digits.
+pub fn words(text: &str) -> Vec<String> {
+    text.split(|x: char| !x.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.chars().flat_map(char::to_lowercase).collect())
+        .collect()
+}
+
+/// Parse a link at the start of `chars`. Returns the text to show instead of the link and how
+/// many characters the link spans.
+fn parse_link(chars: &[char], typ: &DocType) -> Option<(String, usize)> {
+    // Images show their alt text
+    let offset = usize::from(chars[0] == '!');
+    let end = offset + link_end(&chars[offset..])?;
+    let text = chars[offset..end].iter().collect::<String>();
+    let text = text.as_str();
+
+    let (shown, rest) = match parsers::org_link().parse(text) {
+        Ok(((_, Some(description)), rest)) => (description, rest),
+        // Wiki links may have an alias after `|`
+        Ok(((target, None), rest)) if matches!(typ, DocType::Markdown) => {
+            let shown = match target.split_once('|') {
+                Some((_, alias)) => alias.to_string(),
+                None => target,
+            };
+            (shown, rest)
+        }
+        Ok(((target, None), rest)) => (target, rest),
+        Err(_) => match parsers::md_link().parse(text) {
+            Ok(((target, description), rest)) => (description.unwrap_or(target), rest),
+            Err(_) => return None,
+        },
+    };
+
+    Some((shown, offset + text.chars().count() - rest.chars().count()))
+}
+
+/// Where the link at the start of `chars` ends at the latest, which is after its closing bracket:
+/// `[[target]]`, `[[target][description]]` or `[description](target)`. Neither the targets nor the
+/// descriptions of links contain `]`.
+fn link_end(chars: &[char]) -> Option<usize> {
+    let close = chars.iter().position(|x| *x == ']')?;
+    let end = match chars.get(close + 1)? {
+        ']' => close + 1,
+        '[' => close + 1 + chars[close + 1..].iter().position(|x| *x == ']')? + 1,
+        '(' => close + 1 + chars[close + 1..].iter().position(|x| *x == ')')?,
+        _ => return None,
+    };
+    (end < chars.len()).then_some(end + 1)
+}
+
+/// Parse an emphasis, like `*x*`, `**x**` or `=x=`, at the start of `chars`. Returns the text
+/// inside the markers and how many characters the whole emphasis spans.
+fn parse_emphasis(chars: &[char]) -> Option<(String, usize)> {
+    let marker = chars[0];
+    let width = chars.iter().take(3).take_while(|x| **x == marker).count();
+
+    // The emphasized text can't start with a whitespace
+    if chars.get(width).is_none_or(|x| x.is_whitespace()) {
+        return None;
+    }
+
+    let mut idx = width + 1;
+    while idx + width <= chars.len() {
+        let is_closing = chars[idx..idx + width].iter().all(|x| *x == marker)
+            && !chars[idx - 1].is_whitespace()
+            && chars
+                .get(idx + width)
+                .is_none_or(|x| is_closing_boundary(*x, marker));
+        if is_closing {
+            let inner = chars[width..idx].iter().collect();
+            return Some((inner, idx + width));
+        }
+        idx += 1;
+    }
+
+    None
+}
+
+fn is_marker(chr: char, typ: &DocType) -> bool {
+    match typ {
+        DocType::OrgMode => matches!(chr, '*' | '/' | '=' | '~' | '_' | '+'),
+        DocType::Markdown => matches!(chr, '*' | '_' | '~' | '`'),
+    }
+}
+
+/// Is the content of the emphasis shown as is, without interpreting the markup inside of it?
+fn is_verbatim(chr: char) -> bool {
+    matches!(chr, '=' | '~' | '`')
+}
+
+fn is_opening_boundary(chr: char) -> bool {
+    chr.is_whitespace() || matches!(chr, '(' | '{' | '[' | '"' | '\'' | '-')
+}
+
+fn is_closing_boundary(chr: char, marker: char) -> bool {
+    chr != marker && !chr.is_alphanumeric()
+}
+
+#[test]
+fn test_strip() {
+    let org = DocType::OrgMode;
+    assert_eq!(strip("*important* deadline", &org), "important deadline");
+    assert_eq!(
+        strip("/emph/, =co*de*=, ~verb~ and _under_ +del+", &org),
+        "emph, co*de*, verb and under del"
+    );
+    assert_eq!(
+        strip("see [[file:x.org][the *x* note]]", &org),
+        "see the x note"
+    );
+    assert_eq!(strip("see [[Some Note]]", &org), "see Some Note");
+    assert_eq!(
+        strip("* Header, 2*3*4 and snake_case_name", &org),
+        "* Header, 2*3*4 and snake_case_name"
+    );
+
+    let md = DocType::Markdown;
+    assert_eq!(
+        strip("**bold** and __x__ `code` ~~gone~~", &md),
+        "bold and x code gone"
+    );
+    assert_eq!(
+        strip("[desc](x.md) ![alt](pic.png) [[note|alias]]", &md),
+        "desc alt alias"
+    );
+    assert_eq!(strip("a * b * c", &md), "a * b * c");
+    assert_eq!(strip("[a](b [[c]] [d]", &md), "[a](b c [d]");
+    assert_eq!(strip("*not\nbold* [not\n](link)", &org), "*not\nbold* [not\n](link)");
+}
+
+#[test]
+fn test_words() {
+    assert_eq!(
+        words("*Important* note-taking, 2021"),
+        vec!["important", "note", "taking", "2021"]
+    );
+}
@@ -0,0 +1,452 @@
+//! Matchers for the fuzzy terms of the query, see `--matcher`. The fzf matcher and the scores of
+//! the substring and word matchers follow fzf's scoring: every matched character scores the same,
+//! characters at the start of words get a bonus and gaps between the matched characters cost.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+use crate::args::{Args, CaseMode, MatcherKind};
+
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_BOUNDARY: i64 = SCORE_MATCH / 2;
+const BONUS_BOUNDARY_WHITE: i64 = BONUS_BOUNDARY + 2;
+const BONUS_BOUNDARY_DELIMITER: i64 = BONUS_BOUNDARY + 1;
+const BONUS_NON_WORD: i64 = SCORE_MATCH / 2;
+const BONUS_CAMEL_123: i64 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+const BONUS_CONSECUTIVE: i64 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+/// Largest size of the tables of the fzf matcher, longer texts are matched greedily like fzf does.
+const MAX_TABLE_SIZE: usize = 100 * 1024;
+
+pub trait Matcher: Send + Sync {
+    /// Match the pattern against the text. Returns the score and the indices of the matched
+    /// characters (not bytes) of the text, or `None` if the text does not match.
+    fn indices(&self, text: &str, pattern: &str) -> Option<(i64, Vec<usize>)>;
+
+    fn score(&self, text: &str, pattern: &str) -> Option<i64> {
+        self.indices(text, pattern).map(|(score, _)| score)
+    }
+}
+
+/// The matcher that is selected with `--matcher`, `--case`, `--word-boundary` and `--min-score`.
+pub fn from_args(args: &Args) -> Box<dyn Matcher> {
+    let matcher: Box<dyn Matcher> = match args.matcher {
+        MatcherKind::Skim => Box::new(Skim::new(args.case)),
+        MatcherKind::Fzf => Box::new(Fzf { case: args.case }),
+        MatcherKind::Substring => Box::new(Substring {
+            case: args.case,
+            whole_word: false,
+        }),
+        MatcherKind::Word => Box::new(Substring {
+            case: args.case,
+            whole_word: true,
+        }),
+    };
+
+    if args.word_boundary || args.min_score.is_some() {
+        Box::new(Filtered {
+            matcher,
+            word_boundary: args.word_boundary,
+            min_score: args.min_score,
+        })
+    } else {
+        matcher
+    }
+}
+
+/// Should the pattern be matched without caring about the case?
+fn ignores_case(case: CaseMode, pattern: &str) -> bool {
+    match case {
+        CaseMode::Smart => !pattern.chars().any(char::is_uppercase),
+        CaseMode::Ignore => true,
+        CaseMode::Respect => false,
+    }
+}
+
+/// Characters of the text, lowercased if the case is ignored. Every character is kept as one
+/// character, so that the indices stay the same.
+fn chars(text: &str, ignore_case: bool) -> Vec<char> {
+    text.chars()
+        .map(|x| match ignore_case {
+            true => x.to_lowercase().next().unwrap_or(x),
+            false => x,
+        })
+        .collect()
+}
+
+/// Skim's matcher, see [`SkimMatcherV2`].
+pub struct Skim {
+    case: CaseMode,
+    ignore_case: SkimMatcherV2,
+    respect_case: SkimMatcherV2,
+}
+
+impl Skim {
+    pub fn new(case: CaseMode) -> Skim {
+        Skim {
+            case,
+            ignore_case: SkimMatcherV2::default().ignore_case(),
+            respect_case: SkimMatcherV2::default().respect_case(),
+        }
+    }
+}
+
+impl Matcher for Skim {
+    fn indices(&self, text: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        match ignores_case(self.case, pattern) {
+            true => self.ignore_case.fuzzy_indices(text, pattern),
+            false => self.respect_case.fuzzy_indices(text, pattern),
+        }
+    }
+}
+
+/// fzf's default algorithm (FuzzyMatchV2), which finds the best scoring match instead of the
+/// first one. Like in fzf, texts that are too long for it are matched with [`greedy_match`].
+pub struct Fzf {
+    case: CaseMode,
+}
+
+impl Matcher for Fzf {
+    fn indices(&self, text: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        let ignore_case = ignores_case(self.case, pattern);
+        let text = chars(text, ignore_case);
+        let pattern = chars(pattern, ignore_case);
+        if pattern.is_empty() {
+            return Some((0, vec![]));
+        }
+
+        let (rows, cols) = (pattern.len(), text.len());
+        let bonuses = bonuses(&text);
+        if rows * cols > MAX_TABLE_SIZE {
+            return greedy_match(&text, &pattern, &bonuses);
+        }
+
+        // Best score of matching pattern[..=i] with its last character at or before text[j],
+        // the number of consecutive matched characters ending at text[j] and whether the score
+        // comes from matching text[j]
+        let mut scores = vec![None; rows * cols];
+        let mut consecutive = vec![0; rows * cols];
+        let mut is_match = vec![false; rows * cols];
+
+        for (i, &expected) in pattern.iter().enumerate() {
+            for j in i..cols {
+                let idx = i * cols + j;
+                let left = (j > 0).then(|| scores[idx - 1]).flatten().map(|x: i64| {
+                    let in_gap = consecutive[idx - 1] == 0;
+                    x + if in_gap {
+                        SCORE_GAP_EXTENSION
+                    } else {
+                        SCORE_GAP_START
+                    }
+                });
+
+                let diagonal = match (i, j) {
+                    (0, _) => Some((bonuses[j] * BONUS_FIRST_CHAR_MULTIPLIER, 1)),
+                    (_, 0) => None,
+                    _ => scores[idx - cols - 1].map(|score| {
+                        let mut count = consecutive[idx - cols - 1] + 1;
+                        let mut bonus = bonuses[j];
+                        if count > 1 {
+                            let first = bonuses[j + 1 - count];
+                            if bonus >= BONUS_BOUNDARY && bonus > first {
+                                count = 1;
+                            } else {
+                                bonus = bonus.max(first).max(BONUS_CONSECUTIVE);
+                            }
+                        }
+                        (score + bonus, count)
+                    }),
+                };
+                let matched = (text[j] == expected)
+                    .then_some(diagonal)
+                    .flatten()
+                    .map(|(score, count)| (score + SCORE_MATCH, count));
+
+                match (matched, left) {
+                    (Some((score, count)), left) if left.is_none_or(|x| score >= x) => {
+                        scores[idx] = Some(score);
+                        consecutive[idx] = count;
+                        is_match[idx] = true;
+                    }
+                    (_, left) => scores[idx] = left,
+                }
+            }
+        }
+
+        let last = (rows - 1) * cols;
+        let (mut j, score) = (rows - 1..cols)
+            .filter_map(|j| scores[last + j].map(|x| (j, x)))
+            .max_by_key(|(j, score)| (*score, std::cmp::Reverse(*j)))?;
+
+        let mut indices = vec![];
+        for i in (0..rows).rev() {
+            while !is_match[i * cols + j] {
+                j -= 1;
+            }
+            indices.push(j);
+            j = j.saturating_sub(1);
+        }
+        indices.reverse();
+
+        Some((score, indices))
+    }
+}
+
+/// fzf's FuzzyMatchV1, which finds the first occurrence of the pattern and then the shortest match
+/// that ends where it ends, without looking for a better match further in the text.
+fn greedy_match(text: &[char], pattern: &[char], bonuses: &[i64]) -> Option<(i64, Vec<usize>)> {
+    let mut chars = pattern.iter().peekable();
+    let mut end = 0;
+    for (idx, x) in text.iter().enumerate() {
+        if chars.next_if(|y| *y == x).is_some() && chars.peek().is_none() {
+            end = idx + 1;
+            break;
+        }
+    }
+    if chars.peek().is_some() {
+        return None;
+    }
+
+    let mut chars = pattern.iter().rev().peekable();
+    let mut start = end;
+    while chars.peek().is_some() {
+        start -= 1;
+        chars.next_if(|x| **x == text[start]);
+    }
+
+    let mut indices = vec![];
+    let mut score = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0;
+    let mut first = 0;
+    for idx in start..end {
+        if pattern.get(indices.len()) != Some(&text[idx]) {
+            score += if in_gap {
+                SCORE_GAP_EXTENSION
+            } else {
+                SCORE_GAP_START
+            };
+            in_gap = true;
+            consecutive = 0;
+            continue;
+        }
+
+        let mut bonus = bonuses[idx];
+        if consecutive == 0 {
+            first = bonus;
+        } else {
+            if bonus >= BONUS_BOUNDARY && bonus > first {
+                first = bonus;
+            }
+            bonus = bonus.max(first).max(BONUS_CONSECUTIVE);
+        }
+        if indices.is_empty() {
+            bonus *= BONUS_FIRST_CHAR_MULTIPLIER;
+        }
+        score += SCORE_MATCH + bonus;
+        in_gap = false;
+        consecutive += 1;
+        indices.push(idx);
+    }
+
+    Some((score, indices))
+}
+
+/// Matches the pattern as it is, anywhere in the text or only as a whole word.
+pub struct Substring {
+    case: CaseMode,
+    whole_word: bool,
+}
+
+impl Matcher for Substring {
+    fn indices(&self, text: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        let ignore_case = ignores_case(self.case, pattern);
+        let text = chars(text, ignore_case);
+        let pattern = chars(pattern, ignore_case);
+        if pattern.is_empty() {
+            return Some((0, vec![]));
+        }
+
+        let bonuses = bonuses(&text);
+        let is_word = |x: Option<&char>| x.is_some_and(|x| x.is_alphanumeric());
+
+        (0..text.len())
+            .filter(|&start| text[start..].starts_with(&pattern))
+            .filter(|&start| {
+                let end = start + pattern.len();
+                !self.whole_word
+                    || (!is_word(start.checked_sub(1).and_then(|x| text.get(x)))
+                        && !is_word(text.get(end)))
+            })
+            .map(|start| (run_score(&bonuses, start, pattern.len()), start))
+            .max_by_key(|(score, start)| (*score, std::cmp::Reverse(*start)))
+            .map(|(score, start)| (score, (start..start + pattern.len()).collect()))
+    }
+}
+
+/// Drops the matches of another matcher that don't start at a word or that score too low.
+struct Filtered {
+    matcher: Box<dyn Matcher>,
+    word_boundary: bool,
+    min_score: Option<i64>,
+}
+
+impl Matcher for Filtered {
+    fn indices(&self, text: &str, pattern: &str) -> Option<(i64, Vec<usize>)> {
+        let (score, indices) = self.matcher.indices(text, pattern)?;
+
+        if self.min_score.is_some_and(|x| score < x) {
+            return None;
+        }
+
+        if self.word_boundary {
+            let previous = match indices.first() {
+                Some(0) | None => None,
+                Some(x) => text.chars().nth(x - 1),
+            };
+            if previous.is_some_and(|x| x.is_alphanumeric()) {
+                return None;
+            }
+        }
+
+        Some((score, indices))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    White,
+    NonWord,
+    Delimiter,
+    Lower,
+    Upper,
+    Letter,
+    Number,
+}
+
+impl CharClass {
+    fn of(x: char) -> CharClass {
+        match x {
+            x if x.is_whitespace() => CharClass::White,
+            '/' | ',' | ':' | ';' | '|' => CharClass::Delimiter,
+            x if x.is_lowercase() => CharClass::Lower,
+            x if x.is_uppercase() => CharClass::Upper,
+            x if x.is_numeric() => CharClass::Number,
+            x if x.is_alphabetic() => CharClass::Letter,
+            _ => CharClass::NonWord,
+        }
+    }
+
+    fn is_word(self) -> bool {
+        !matches!(
+            self,
+            CharClass::White | CharClass::NonWord | CharClass::Delimiter
+        )
+    }
+}
+
+/// Bonus of matching each character of the text, by how it starts a word.
+fn bonuses(text: &[char]) -> Vec<i64> {
+    let mut previous = CharClass::White;
+    text.iter()
+        .map(|x| {
+            let current = CharClass::of(*x);
+            let bonus = match (previous, current) {
+                (CharClass::White, x) if x.is_word() => BONUS_BOUNDARY_WHITE,
+                (CharClass::Delimiter, x) if x.is_word() => BONUS_BOUNDARY_DELIMITER,
+                (CharClass::NonWord, x) if x.is_word() => BONUS_BOUNDARY,
+                (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL_123,
+                (x, CharClass::Number) if x != CharClass::Number => BONUS_CAMEL_123,
+                (_, CharClass::NonWord | CharClass::Delimiter) => BONUS_NON_WORD,
+                (_, CharClass::White) => BONUS_BOUNDARY_WHITE,
+                _ => 0,
+            };
+            previous = current;
+            bonus
+        })
+        .collect()
+}
+
+/// Score of matching `len` consecutive characters from `start`, the same way fzf scores them.
+fn run_score(bonuses: &[i64], start: usize, len: usize) -> i64 {
+    let first = bonuses[start];
+    let mut score = SCORE_MATCH + first * BONUS_FIRST_CHAR_MULTIPLIER;
+    let mut first = first;
+    for &bonus in &bonuses[start + 1..start + len] {
+        if bonus >= BONUS_BOUNDARY && bonus > first {
+            first = bonus;
+        }
+        score += SCORE_MATCH + bonus.max(first).max(BONUS_CONSECUTIVE);
+    }
+    score
+}
+
+#[test]
+fn test_fzf() {
+    let fzf = Fzf {
+        case: CaseMode::Smart,
+    };
+    assert_eq!(fzf.indices("fooBar", "fb").map(|x| x.1), Some(vec![0, 3]));
+    assert_eq!(fzf.indices("foo bar", "FB"), None);
+    assert_eq!(fzf.indices("abc", "abcd"), None);
+
+    // The best match is found, not the first one
+    assert_eq!(
+        fzf.indices("axxxxb ab", "ab").map(|x| x.1),
+        Some(vec![7, 8])
+    );
+
+    // Matches at the start of words score higher
+    assert!(fzf.score("the list", "li") > fzf.score("unclear", "li"));
+
+    // Long texts take the first match, scored the same way
+    let long = format!("{} foo bar ab", "x".repeat(MAX_TABLE_SIZE));
+    let (score, indices) = fzf.indices(&long, "fb").unwrap();
+    assert_eq!(indices, vec![MAX_TABLE_SIZE + 1, MAX_TABLE_SIZE + 5]);
+    assert_eq!(Some(score), fzf.score("foo bar", "fb"));
+    assert_eq!(fzf.indices(&long, "fbz"), None);
+}
+
+#[test]
+fn test_substring() {
+    let substring = Substring {
+        case: CaseMode::Ignore,
+        whole_word: false,
+    };
+    assert_eq!(
+        substring.indices("A Cat", "cat").map(|x| x.1),
+        Some(vec![2, 3, 4])
+    );
+    assert_eq!(substring.indices("cart", "cat"), None);
+
+    let word = Substring {
+        case: CaseMode::Respect,
+        whole_word: true,
+    };
+    assert_eq!(
+        word.indices("concatenate cat", "cat").map(|x| x.1),
+        Some(vec![12, 13, 14])
+    );
+    assert_eq!(word.indices("concatenate", "cat"), None);
+    assert_eq!(word.indices("Cat", "cat"), None);
+}
+
+#[test]
+fn test_filtered() {
+    let filtered = Filtered {
+        matcher: Box::new(Skim::new(CaseMode::Smart)),
+        word_boundary: true,
+        min_score: None,
+    };
+    assert!(filtered.score("the apple", "app").is_some());
+    assert!(filtered.score("snapple", "app").is_none());
+
+    let filtered = Filtered {
+        matcher: Box::new(Skim::new(CaseMode::Smart)),
+        word_boundary: false,
+        min_score: Some(1000),
+    };
+    assert!(filtered.score("apple", "ae").is_none());
+}
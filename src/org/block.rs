@@ -0,0 +1,60 @@
+/// A block, like `#+BEGIN_SRC python :tangle x.py` ... `#+END_SRC` or a fenced code block.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct OrgBlock {
+    /// Type of the block in uppercase, `SRC` in `#+BEGIN_SRC`. Code blocks of other formats are
+    /// `SRC` blocks too.
+    pub kind: String,
+    /// Name of the block, from the `#+NAME:` line before it.
+    pub name: Option<String>,
+    /// Language of a source block, `python` in `#+BEGIN_SRC python`.
+    pub lang: Option<String>,
+    /// Header arguments, like `:tangle x.py`, without the colon of the key.
+    pub args: Vec<(String, String)>,
+    /// On which line does the block start.
+    pub line: usize,
+    /// Lines between the start and the end of the block.
+    pub content: Vec<String>,
+}
+
+impl OrgBlock {
+    /// Value of the header argument with the given key. Empty if the argument has no value.
+    pub fn arg(&self, key: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .rev()
+            .find(|(x, _)| x.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Parse header arguments, like `:tangle x.py :dir /ssh:host:/tmp`. Values may contain
+    /// spaces, they last until the next word that starts with a colon.
+    pub fn parse_args(text: &str) -> Vec<(String, String)> {
+        let mut args: Vec<(String, String)> = vec![];
+        for word in text.split_whitespace() {
+            match (word.strip_prefix(':'), args.last_mut()) {
+                (Some(key), _) if !key.is_empty() => args.push((key.to_string(), String::new())),
+                (_, Some((_, value))) => {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(word);
+                }
+                (_, None) => {}
+            }
+        }
+        args
+    }
+}
+
+#[test]
+fn test_parse_args() {
+    let block = OrgBlock {
+        args: OrgBlock::parse_args(":tangle x.py :dir /ssh:host:/tmp :var a=1 b=2 :noweb"),
+        ..Default::default()
+    };
+    assert_eq!(block.arg("tangle"), Some("x.py"));
+    assert_eq!(block.arg("dir"), Some("/ssh:host:/tmp"));
+    assert_eq!(block.arg("var"), Some("a=1 b=2"));
+    assert_eq!(block.arg("noweb"), Some(""));
+    assert_eq!(block.arg("results"), None);
+}
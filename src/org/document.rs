@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use crate::marks::DocType;
+use crate::org::block::OrgBlock;
+use crate::org::footnote::OrgFootnote;
+use crate::org::header::OrgHeader;
+use crate::org::link::{slugify, OrgLink};
+use crate::org::table::OrgTable;
+use crate::org::target::OrgTarget;
+
+/// A whole parsed file. Unlike the search, which goes through the file line by line and forgets
+/// about it, this keeps the things that need to be looked up across files, like headers and links.
+#[derive(Debug, Clone)]
+pub struct OrgDocument<'a> {
+    pub path: PathBuf,
+    pub doc_type: DocType,
+    /// All headers in the file, in the order they appear.
+    pub headers: Vec<OrgHeader<'a>>,
+    /// All links in the file, in the order they appear.
+    pub links: Vec<OrgLink>,
+    /// All footnote references and definitions in the file, in the order they appear.
+    pub footnotes: Vec<OrgFootnote>,
+    /// All tables in the file, in the order they appear.
+    pub tables: Vec<OrgTable>,
+    /// All `<<targets>>` and `<<<radio targets>>>` in the file, in the order they appear.
+    pub targets: Vec<OrgTarget>,
+    /// All blocks, like source blocks, in the file, in the order they appear.
+    pub blocks: Vec<OrgBlock>,
+}
+
+impl<'a> OrgDocument<'a> {
+    /// Return the header hierarchy that the given line belongs to.
+    pub fn hierarchy(&self, line: usize) -> Vec<OrgHeader<'a>> {
+        let mut result: Vec<OrgHeader> = vec![];
+        for header in self.headers.iter().take_while(|x| x.line <= line) {
+            while result.last().is_some_and(|x| x.depth >= header.depth) {
+                result.pop();
+            }
+            result.push(header.clone());
+        }
+        result
+    }
+
+    /// Find the index of the header with the given text.
+    pub fn find_header(&self, heading: &str) -> Option<usize> {
+        self.headers
+            .iter()
+            .position(|x| x.content.trim() == heading.trim())
+    }
+
+    /// Find the target with the given name, ignoring case.
+    pub fn find_target(&self, name: &str) -> Option<&OrgTarget> {
+        self.targets
+            .iter()
+            .find(|x| x.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Find the index of the header with the given anchor. Anchors are either CUSTOM_ID properties
+    /// or slugified header text.
+    pub fn find_anchor(&self, anchor: &str) -> Option<usize> {
+        self.headers.iter().position(|x| {
+            x.properties.get("CUSTOM_ID").is_some_and(|id| id == anchor)
+                || slugify(&x.content) == anchor
+        })
+    }
+}
@@ -0,0 +1,129 @@
+/// A drawer, like `:LOGBOOK:` ... `:END:`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct OrgDrawer {
+    /// Name of the drawer, `LOGBOOK` in `:LOGBOOK:`.
+    pub name: String,
+    /// On which line does the drawer start.
+    pub line: usize,
+    /// Lines between the start and the `:END:` line.
+    pub content: Vec<String>,
+}
+
+/// Collects drawers while going through a file line by line.
+#[derive(Debug, Default)]
+pub struct DrawerBuilder {
+    current: Option<OrgDrawer>,
+    finished: Option<OrgDrawer>,
+}
+
+impl DrawerBuilder {
+    /// Feed the next line into the builder, along with the lines after it until the next header.
+    /// Returns the name of the drawer that the line belongs to, including the lines that start and
+    /// end the drawer. A line like `:word:` only starts a drawer if an `:END:` line comes after
+    /// it, otherwise it's just text.
+    pub fn push<'l, I>(&mut self, idx: usize, line: &str, rest: I) -> Option<&str>
+    where
+        I: IntoIterator<Item = &'l str>,
+    {
+        self.finished = None;
+
+        let trimmed = line.trim();
+        if self.current.is_some() && is_end(line) {
+            self.finished = self.current.take();
+            return self.finished.as_ref().map(|x| x.name.as_str());
+        }
+
+        match &mut self.current {
+            Some(drawer) => {
+                drawer.content.push(line.to_string());
+            }
+            None => {
+                let name = drawer_name(trimmed)?;
+                if !rest.into_iter().any(is_end) {
+                    return None;
+                }
+                self.current = Some(OrgDrawer {
+                    name: name.to_string(),
+                    line: idx,
+                    content: vec![],
+                });
+            }
+        }
+
+        self.current.as_ref().map(|x| x.name.as_str())
+    }
+
+    /// The drawer that the last pushed line has ended, if there is any.
+    pub fn take_finished(&mut self) -> Option<OrgDrawer> {
+        self.finished.take()
+    }
+
+    /// Forget the drawer that is being built. Drawers can't contain headers, so a drawer that is
+    /// not ended before a header is not a drawer at all.
+    pub fn reset(&mut self) {
+        self.current = None;
+        self.finished = None;
+    }
+}
+
+fn is_end(line: &str) -> bool {
+    line.trim().eq_ignore_ascii_case(":END:")
+}
+
+/// If the line starts a drawer, like `:LOGBOOK:`, return the name of the drawer.
+fn drawer_name(line: &str) -> Option<&str> {
+    let name = line.strip_prefix(':')?.strip_suffix(':')?;
+    let is_name = !name.is_empty()
+        && !name.eq_ignore_ascii_case("END")
+        && name
+            .chars()
+            .all(|x| x.is_alphanumeric() || x == '_' || x == '-');
+
+    is_name.then_some(name)
+}
+
+#[test]
+fn test_drawer_builder() {
+    let lines = [
+        "text",
+        ":LOGBOOK:",
+        "CLOCK: [2021-01-01 Fri 10:00]",
+        ":END:",
+        ":ID: not-a-drawer",
+        ":stray:",
+        "not in a drawer",
+    ];
+    let mut builder = DrawerBuilder::default();
+    let names = lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let rest = lines[idx + 1..].iter().copied();
+            builder.push(idx, line, rest).map(str::to_string)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            None,
+            Some("LOGBOOK".into()),
+            Some("LOGBOOK".into()),
+            Some("LOGBOOK".into()),
+            None,
+            None,
+            None
+        ]
+    );
+
+    builder.push(0, ":NOTES:", [":end:"]);
+    builder.push(1, "a note", []);
+    assert_eq!(builder.push(2, ":end:", []), Some("NOTES"));
+    assert_eq!(
+        builder.take_finished(),
+        Some(OrgDrawer {
+            name: "NOTES".into(),
+            line: 0,
+            content: vec!["a note".into()],
+        })
+    );
+}
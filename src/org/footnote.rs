@@ -0,0 +1,111 @@
+use combine::Parser;
+
+use crate::marks::DocType;
+use crate::parsers;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct OrgFootnote {
+    /// On which line is the footnote found.
+    pub line: usize,
+    /// Label of the footnote, `name` in `[fn:name]` or `[^name]`.
+    pub name: String,
+    /// Text of the footnote if this is a definition, like `[fn:name] text` or `[^name]: text`.
+    /// Inline footnotes, `[fn:name:text]`, are both a reference and a definition.
+    pub definition: Option<String>,
+    /// Is this a reference to the footnote?
+    pub is_reference: bool,
+}
+
+impl OrgFootnote {
+    /// Find all footnote references and definitions in the given line.
+    pub fn parse_all(line: &str, idx: usize, typ: &DocType) -> Vec<OrgFootnote> {
+        let mut footnotes = vec![];
+        let mut rest = line;
+
+        while let Some(pos) = rest.find('[') {
+            let is_line_start = rest.len() - pos == line.len();
+            rest = &rest[pos..];
+
+            let parsed = match typ {
+                DocType::OrgMode => parsers::org_footnote().parse(rest).ok(),
+                DocType::Markdown => parsers::md_footnote().parse(rest).ok(),
+            };
+
+            match parsed {
+                // Anonymous footnotes, [fn::text], can't be referenced from anywhere else
+                Some(((name, _), next)) if name.is_empty() => rest = next,
+                Some(((name, inline), next)) => {
+                    let definition_start = match typ {
+                        DocType::OrgMode => Some(next),
+                        DocType::Markdown => next.strip_prefix(':'),
+                    };
+
+                    let footnote = match (inline, definition_start) {
+                        (Some(text), _) => OrgFootnote {
+                            line: idx,
+                            name,
+                            definition: Some(text),
+                            is_reference: true,
+                        },
+                        (None, Some(text)) if is_line_start => OrgFootnote {
+                            line: idx,
+                            name,
+                            definition: Some(text.trim().into()),
+                            is_reference: false,
+                        },
+                        (None, _) => OrgFootnote {
+                            line: idx,
+                            name,
+                            definition: None,
+                            is_reference: true,
+                        },
+                    };
+
+                    footnotes.push(footnote);
+                    rest = next;
+                }
+                None => rest = &rest[1..],
+            }
+        }
+
+        footnotes
+    }
+}
+
+#[test]
+fn test_parse_footnotes() {
+    assert_eq!(
+        OrgFootnote::parse_all("[fn:1] The text [fn:2]", 3, &DocType::OrgMode),
+        vec![
+            OrgFootnote {
+                line: 3,
+                name: "1".into(),
+                definition: Some("The text [fn:2]".into()),
+                is_reference: false,
+            },
+            OrgFootnote {
+                line: 3,
+                name: "2".into(),
+                definition: None,
+                is_reference: true,
+            }
+        ]
+    );
+
+    let footnotes = OrgFootnote::parse_all(
+        "Some text[fn:1] and [fn:x:inline] [fn::anon]",
+        0,
+        &DocType::OrgMode,
+    );
+    assert_eq!(
+        footnotes
+            .iter()
+            .map(|x| (x.name.as_str(), x.definition.as_deref(), x.is_reference))
+            .collect::<Vec<_>>(),
+        vec![("1", None, true), ("x", Some("inline"), true)]
+    );
+
+    let footnotes = OrgFootnote::parse_all("[^note]: Markdown", 0, &DocType::Markdown);
+    assert_eq!(footnotes[0].definition, Some("Markdown".into()));
+    assert!(!footnotes[0].is_reference);
+}
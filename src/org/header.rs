@@ -1,23 +1,133 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
+
+use combine::Parser;
 
 use crate::args::Args;
 use crate::org::datetime::OrgDateTime;
+use crate::org::drawer::OrgDrawer;
+use crate::parsers;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct OrgPriority(pub String);
 
-impl PartialOrd for OrgPriority {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if self.0.chars().all(|x| x.is_alphabetic()) { // A > B
-            other.0.partial_cmp(&self.0)
-        } else if self.0.chars().all(|x| x.is_digit(10)) { // 2 > 1
-            self.0.parse::<u32>().unwrap_or(0).partial_cmp(&other.0.parse::<u32>().unwrap_or(0))
-        } else {
-            self.0.partial_cmp(&other.0)
+impl OrgPriority {
+    /// Priorities are either single letters or numbers. Returns whether the priority is a number,
+    /// along with its value.
+    fn key(&self) -> Option<(bool, u32)> {
+        let mut chars = self.0.chars();
+        match (chars.next(), chars.next()) {
+            (Some(x), None) if x.is_ascii_uppercase() => Some((false, x as u32)),
+            _ => self.0.parse().ok().map(|x| (true, x)),
+        }
+    }
+}
+
+impl fmt::Display for OrgPriority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The priorities that headers can have, like org's `#+PRIORITIES: A C B`. Priorities are ordered
+/// from the highest to the lowest, in either direction, like `A C B` or `1 10 5`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PriorityRange {
+    pub highest: OrgPriority,
+    pub lowest: OrgPriority,
+    /// Priority of the headers without a priority cookie, `None` if they have no priority.
+    pub default: Option<OrgPriority>,
+}
+
+impl Default for PriorityRange {
+    fn default() -> Self {
+        PriorityRange {
+            highest: OrgPriority("A".into()),
+            lowest: OrgPriority("C".into()),
+            default: Some(OrgPriority("B".into())),
         }
     }
 }
 
+impl PriorityRange {
+    /// Parse a range written as `HIGHEST LOWEST DEFAULT`, like `A C B`.
+    pub fn parse(text: &str) -> Result<PriorityRange, String> {
+        let priorities = text
+            .split_whitespace()
+            .map(|x| OrgPriority(x.to_uppercase()))
+            .collect::<Vec<_>>();
+        let [highest, lowest, default] = <[OrgPriority; 3]>::try_from(priorities).map_err(|_| {
+            format!("expected the highest, the lowest and the default priority, got `{}`", text)
+        })?;
+
+        let (is_number, _) = highest
+            .key()
+            .ok_or_else(|| format!("invalid priority `{}`", highest))?;
+        if lowest.key().is_none_or(|(x, _)| x != is_number) {
+            return Err(format!("`{}` and `{}` are not of the same kind", highest, lowest));
+        }
+
+        let range = PriorityRange {
+            highest,
+            lowest,
+            default: Some(default.clone()),
+        };
+        if !range.contains(&default) {
+            return Err(format!("default priority `{}` is out of the range", default));
+        }
+
+        Ok(range)
+    }
+
+    /// The range that the given priorities span, without a default priority. Lower letters and
+    /// numbers are higher, like in `A C B` and `1 10 5`. Only the priorities of the same kind as
+    /// the first one are counted.
+    pub fn spanning(priorities: &[OrgPriority]) -> Option<PriorityRange> {
+        let (is_number, _) = priorities.iter().find_map(OrgPriority::key)?;
+        let values = priorities
+            .iter()
+            .filter(|x| x.key().is_some_and(|(x, _)| x == is_number));
+        let highest = values.clone().min_by_key(|x| x.key())?;
+        let lowest = values.max_by_key(|x| x.key())?;
+
+        Some(PriorityRange {
+            highest: highest.clone(),
+            lowest: lowest.clone(),
+            default: None,
+        })
+    }
+
+    /// Is the priority between the highest and the lowest priority?
+    pub fn contains(&self, priority: &OrgPriority) -> bool {
+        self.rank(priority).is_some()
+    }
+
+    /// How far the priority is from the highest priority, `None` if it's out of the range.
+    fn rank(&self, priority: &OrgPriority) -> Option<u32> {
+        let (is_number, highest) = self.highest.key()?;
+        let (_, lowest) = self.lowest.key()?;
+        let (_, value) = priority.key().filter(|(x, _)| *x == is_number)?;
+
+        let is_in_range = (highest.min(lowest)..=highest.max(lowest)).contains(&value);
+        is_in_range.then(|| highest.abs_diff(value))
+    }
+
+    /// How high the priority is, from 1.0 for the highest priority to 0.0 for the lowest. `None`
+    /// if it's out of the range.
+    pub fn importance(&self, priority: &OrgPriority) -> Option<f64> {
+        let span = self.rank(&self.lowest)?;
+        let rank = self.rank(priority)?;
+        Some(if span == 0 { 1.0 } else { 1.0 - rank as f64 / span as f64 })
+    }
+
+    /// Compare two priorities, higher priorities are greater. Priorities that are out of the range
+    /// can't be compared.
+    pub fn compare(&self, a: &OrgPriority, b: &OrgPriority) -> Option<Ordering> {
+        Some(self.rank(b)?.cmp(&self.rank(a)?))
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub enum OrgTodo {
     TODO,
@@ -25,6 +135,17 @@ pub enum OrgTodo {
     Other(String),
 }
 
+impl OrgTodo {
+    /// The keyword as it's written in the header.
+    pub fn keyword(&self) -> &str {
+        match self {
+            OrgTodo::TODO => "TODO",
+            OrgTodo::DONE => "DONE",
+            OrgTodo::Other(x) => x,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OrgHeader<'a> {
     /// Args
@@ -39,18 +160,61 @@ pub struct OrgHeader<'a> {
     pub tags: Vec<String>,
     /// Properties found in :PROPERTIES: block of an org header. Means nothing for markdown headers.
     pub properties: HashMap<String, String>,
+    /// Drawers found in the section of the header, other than the :PROPERTIES: drawer that
+    /// directly follows the header.
+    pub drawers: Vec<OrgDrawer>,
     /// SCHEDULED/DEADLINE status of the header.
     pub datetime: Option<OrgDateTime>,
     /// TODO state
     pub todo: Option<OrgTodo>,
-    /// The priority, like [#...], ... being anything
+    /// The priority, like [#...], ... being anything. Headers without one have the default
+    /// priority of the file, see [`PriorityRange`].
     pub priority: Option<OrgPriority>,
+    /// Is this a COMMENT header?
+    pub is_comment: bool,
+}
+
+impl OrgHeader<'_> {
+    /// Add a drawer that is found in the section of the header. :PROPERTIES: drawers that don't
+    /// directly follow the header are still read as properties.
+    pub fn add_drawer(&mut self, drawer: OrgDrawer) {
+        if drawer.name.eq_ignore_ascii_case("PROPERTIES") {
+            for line in &drawer.content {
+                if let Ok(((key, val), _)) = parsers::org_property().parse(line.as_str()) {
+                    self.properties.entry(key).or_insert(val);
+                }
+            }
+        }
+
+        self.drawers.push(drawer);
+    }
 }
 
 #[test]
 fn test_priority_ordering() {
-    assert!(OrgPriority("A".into()) > OrgPriority("B".into()));
-    assert!(OrgPriority("3".into()) > OrgPriority("2".into()));
-    assert!(OrgPriority("15".into()) > OrgPriority("13".into()));
-    assert!(OrgPriority("A".into()) == OrgPriority("A".into()));
+    let priority = |x: &str| OrgPriority(x.into());
+
+    let range = PriorityRange::default();
+    assert_eq!(range.compare(&priority("A"), &priority("B")), Some(Ordering::Greater));
+    assert_eq!(range.compare(&priority("C"), &priority("B")), Some(Ordering::Less));
+    assert_eq!(range.compare(&priority("A"), &priority("D")), None);
+    assert_eq!(range.compare(&priority("A"), &priority("1")), None);
+
+    let range = PriorityRange::parse("1 15 5").unwrap();
+    assert_eq!(range.compare(&priority("2"), &priority("3")), Some(Ordering::Greater));
+    assert_eq!(range.compare(&priority("15"), &priority("13")), Some(Ordering::Less));
+    assert!(!range.contains(&priority("16")));
+    assert_eq!(range.importance(&priority("1")), Some(1.0));
+    assert_eq!(range.importance(&priority("15")), Some(0.0));
+
+    let range = PriorityRange::parse("10 1 5").unwrap();
+    assert_eq!(range.compare(&priority("3"), &priority("2")), Some(Ordering::Greater));
+
+    assert!(PriorityRange::parse("A C").is_err());
+    assert!(PriorityRange::parse("A 5 B").is_err());
+    assert!(PriorityRange::parse("A C D").is_err());
+    assert_eq!(PriorityRange::parse("a e c").unwrap().default, Some(priority("C")));
+
+    let range = PriorityRange::spanning(&[priority("3"), priority("A"), priority("1")]).unwrap();
+    assert_eq!((range.highest, range.lowest, range.default), (priority("1"), priority("3"), None));
 }
@@ -0,0 +1,294 @@
+use combine::Parser;
+
+use crate::marks::DocType;
+use crate::parsers;
+
+/// Where inside of a file a link points to.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum LinkSearch {
+    /// `*Heading` in org links, `#Heading` in wiki-style links.
+    Heading(String),
+    /// `#custom-id` in org links, `#anchor` in markdown links.
+    Anchor(String),
+    /// A line number, like `file:x.org::42`.
+    Line(usize),
+    /// Anything else. Matches a dedicated target or a header with the same text.
+    Text(String),
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum LinkTarget {
+    /// `file:x.org::*Heading`, `./x.org`, `x.md#anchor`...
+    File {
+        path: String,
+        search: Option<LinkSearch>,
+    },
+    /// `id:...`, points to the header with the given ID property.
+    Id(String),
+    /// A link into the same file, like `[[*Heading]]` or `[text](#anchor)`.
+    Internal(LinkSearch),
+    /// Wiki-style `[[Note Name]]`, points to a note with the given file name (without extension).
+    Wiki {
+        name: String,
+        search: Option<LinkSearch>,
+    },
+    /// Anything with a scheme that is not handled above, like `https:` or `mailto:`.
+    External(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct OrgLink {
+    /// On which line is the link found.
+    pub line: usize,
+    /// The link target as it's written in the file.
+    pub raw: String,
+    /// The description part of the link, if there is any.
+    pub description: Option<String>,
+    pub target: LinkTarget,
+    /// The full line that the link is found on.
+    pub content: String,
+}
+
+impl OrgLink {
+    /// Find all links in the given line.
+    pub fn parse_all(line: &str, idx: usize, typ: &DocType) -> Vec<OrgLink> {
+        let mut links = vec![];
+        let mut rest = line;
+
+        while let Some(pos) = rest.find('[') {
+            rest = &rest[pos..];
+
+            let parsed = match parsers::org_link().parse(rest) {
+                Ok(((raw, description), next)) => Some((raw, description, next, true)),
+                Err(_) if matches!(typ, DocType::Markdown) => parsers::md_link()
+                    .parse(rest)
+                    .ok()
+                    .map(|((raw, description), next)| (raw, description, next, false)),
+                Err(_) => None,
+            };
+
+            match parsed {
+                Some((raw, description, next, is_bracket)) => {
+                    let (target, description) = if is_bracket {
+                        LinkTarget::from_bracket_link(&raw, description, typ)
+                    } else {
+                        (LinkTarget::from_md_link(&raw), description)
+                    };
+
+                    links.push(OrgLink {
+                        line: idx,
+                        raw,
+                        description,
+                        target,
+                        content: line.to_string(),
+                    });
+                    rest = next;
+                }
+                None => rest = &rest[1..],
+            }
+        }
+
+        links
+    }
+}
+
+impl LinkTarget {
+    /// Parse targets of `[[target][description]]` style links. For markdown files these are wiki-style
+    /// links, `[[Note Name#Heading|alias]]`.
+    fn from_bracket_link(
+        raw: &str,
+        description: Option<String>,
+        typ: &DocType,
+    ) -> (LinkTarget, Option<String>) {
+        if let DocType::Markdown = typ {
+            let (name, alias) = match raw.split_once('|') {
+                Some((name, alias)) => (name, Some(alias.to_string())),
+                None => (raw, description),
+            };
+            let (name, search) = match name.split_once('#') {
+                Some((name, heading)) => (name, Some(LinkSearch::Heading(heading.into()))),
+                None => (name, None),
+            };
+
+            let target = if name.is_empty() {
+                LinkTarget::Internal(search.unwrap_or(LinkSearch::Text(String::new())))
+            } else {
+                LinkTarget::Wiki {
+                    name: name.trim().into(),
+                    search,
+                }
+            };
+            return (target, alias);
+        }
+
+        let target = if let Some(rest) = raw.strip_prefix("file:") {
+            LinkTarget::file_with_search(rest, "::", LinkSearch::from_org)
+        } else if let Some(id) = raw.strip_prefix("id:") {
+            LinkTarget::Id(id.trim().into())
+        } else if raw.starts_with('*') || raw.starts_with('#') {
+            LinkTarget::Internal(LinkSearch::from_org(raw))
+        } else if is_path_like(raw) {
+            LinkTarget::file_with_search(raw, "::", LinkSearch::from_org)
+        } else if has_scheme(raw) {
+            LinkTarget::External(raw.into())
+        } else {
+            LinkTarget::Internal(LinkSearch::Text(raw.into()))
+        };
+
+        (target, description)
+    }
+
+    /// Parse targets of `[description](target)` style links.
+    fn from_md_link(raw: &str) -> LinkTarget {
+        if let Some(anchor) = raw.strip_prefix('#') {
+            LinkTarget::Internal(LinkSearch::Anchor(anchor.into()))
+        } else if has_scheme(raw) && !raw.starts_with("file:") {
+            LinkTarget::External(raw.into())
+        } else {
+            let raw = raw.strip_prefix("file:").unwrap_or(raw);
+            LinkTarget::file_with_search(raw, "#", |x| LinkSearch::Anchor(x.into()))
+        }
+    }
+
+    fn file_with_search(raw: &str, sep: &str, search: fn(&str) -> LinkSearch) -> LinkTarget {
+        match raw.split_once(sep) {
+            Some((path, rest)) if !rest.is_empty() => LinkTarget::File {
+                path: percent_decode(path),
+                search: Some(search(rest)),
+            },
+            Some((path, _)) => LinkTarget::File {
+                path: percent_decode(path),
+                search: None,
+            },
+            None => LinkTarget::File {
+                path: percent_decode(raw),
+                search: None,
+            },
+        }
+    }
+}
+
+impl LinkSearch {
+    /// Parse the search part of an org link, the part that comes after `::` in `file:x.org::*Heading`.
+    pub fn from_org(search: &str) -> LinkSearch {
+        if let Some(heading) = search.strip_prefix('*') {
+            LinkSearch::Heading(heading.trim().into())
+        } else if let Some(anchor) = search.strip_prefix('#') {
+            LinkSearch::Anchor(anchor.into())
+        } else if let Ok(line) = search.parse::<usize>() {
+            LinkSearch::Line(line)
+        } else {
+            LinkSearch::Text(search.into())
+        }
+    }
+}
+
+/// Turn a header into a markdown anchor, the way GitHub does it.
+pub fn slugify(header: &str) -> String {
+    header
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|x| match x {
+            ' ' => Some('-'),
+            x if x.is_alphanumeric() || x == '-' || x == '_' => Some(x),
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_path_like(raw: &str) -> bool {
+    ["/", "./", "../", "~/"].iter().any(|x| raw.starts_with(x))
+}
+
+fn has_scheme(raw: &str) -> bool {
+    match raw.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|x: char| x.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || x == '+' || x == '-' || x == '.')
+        }
+        None => false,
+    }
+}
+
+/// Decode `%20` style escapes that are commonly found in markdown links.
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let decoded = if bytes[i] == b'%' {
+            raw.get(i + 1..i + 3)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+        } else {
+            None
+        };
+
+        match decoded {
+            Some(x) => {
+                result.push(x);
+                i += 3;
+            }
+            None => {
+                result.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(result).unwrap_or_else(|_| raw.to_string())
+}
+
+#[test]
+fn test_parse_org_links() {
+    let links = OrgLink::parse_all(
+        "See [[file:x.org::*Heading][desc]], [[id:abc-123]] and [[https://example.com][site]]",
+        0,
+        &DocType::OrgMode,
+    );
+    let targets = links.into_iter().map(|x| x.target).collect::<Vec<_>>();
+    assert_eq!(
+        targets,
+        vec![
+            LinkTarget::File {
+                path: "x.org".into(),
+                search: Some(LinkSearch::Heading("Heading".into()))
+            },
+            LinkTarget::Id("abc-123".into()),
+            LinkTarget::External("https://example.com".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_md_links() {
+    let links = OrgLink::parse_all(
+        "A [text](sub/My%20Note.md#anchor), [[Note Name|alias]] and [x](#local)",
+        0,
+        &DocType::Markdown,
+    );
+    assert_eq!(links[1].description, Some("alias".into()));
+    let targets = links.into_iter().map(|x| x.target).collect::<Vec<_>>();
+    assert_eq!(
+        targets,
+        vec![
+            LinkTarget::File {
+                path: "sub/My Note.md".into(),
+                search: Some(LinkSearch::Anchor("anchor".into()))
+            },
+            LinkTarget::Wiki {
+                name: "Note Name".into(),
+                search: None
+            },
+            LinkTarget::Internal(LinkSearch::Anchor("local".into())),
+        ]
+    );
+}
+
+#[test]
+fn test_slugify() {
+    assert_eq!(slugify("Hello, World: Again"), "hello-world-again");
+}
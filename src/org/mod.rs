@@ -1,2 +1,10 @@
+pub mod block;
 pub mod datetime;
+pub mod document;
+pub mod drawer;
+pub mod footnote;
 pub mod header;
+pub mod link;
+pub mod settings;
+pub mod table;
+pub mod target;
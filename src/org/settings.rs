@@ -0,0 +1,341 @@
+use std::path::{Path, PathBuf};
+
+use crate::formats::SourceText;
+use crate::org::header::{OrgTodo, PriorityRange};
+use crate::utils::file_utils;
+
+/// Settings of an org file that come from its keywords, like `#+TODO:`, including the ones that
+/// come from its `#+SETUPFILE:`s.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileSettings {
+    /// Keywords from `#+TODO:`, `#+SEQ_TODO:` and `#+TYP_TODO:`. If there are none, any uppercase
+    /// word at the start of a header is a TODO keyword.
+    pub todo_keywords: Vec<String>,
+    /// Tags from `#+FILETAGS:`, every header in the file inherits them.
+    pub filetags: Vec<String>,
+    /// Priorities from `#+PRIORITIES:`. Files without it whose priority cookies are out of
+    /// `--priorities` get the range of their cookies instead, see `Marks::read_settings`.
+    pub priorities: Option<PriorityRange>,
+    /// Setup files that the settings are read from, including the ones in other setup files.
+    pub setup_files: Vec<PathBuf>,
+}
+
+impl FileSettings {
+    /// Collect the settings from the contents of the file at `path`. Setup files are resolved
+    /// relative to the file that refers to them.
+    pub fn collect(path: &Path, text: &str) -> FileSettings {
+        let mut settings = FileSettings::default();
+        settings.read(path, text, &mut vec![canonical(path)]);
+        settings
+    }
+
+    /// `chain` is the list of files that lead to this one, used for detecting cycles.
+    fn read(&mut self, path: &Path, text: &str, chain: &mut Vec<PathBuf>) {
+        for (idx, line) in text.lines().enumerate() {
+            let Some((key, value)) = keyword(line) else {
+                continue;
+            };
+
+            match key.as_str() {
+                "TODO" | "SEQ_TODO" | "TYP_TODO" => {
+                    // Keywords may have fast access keys, like TODO(t)
+                    let keywords = value
+                        .split_whitespace()
+                        .filter(|x| *x != "|")
+                        .map(|x| x.split('(').next().unwrap_or(x).to_string());
+                    self.todo_keywords.extend(keywords);
+                }
+                "FILETAGS" => {
+                    let tags = value
+                        .split(|x: char| x == ':' || x.is_whitespace())
+                        .filter(|x| !x.is_empty())
+                        .map(String::from);
+                    self.filetags.extend(tags);
+                }
+                "PRIORITIES" => match PriorityRange::parse(value) {
+                    Ok(range) => self.priorities = Some(range),
+                    Err(err) => eprintln!("warning: {}:{}: {}", path.display(), idx + 1, err),
+                },
+                "SETUPFILE" => {
+                    let file = unquote(value);
+                    // Remote setup files are not fetched
+                    if file.contains("://") {
+                        continue;
+                    }
+
+                    let Some((setup, text)) = open(path, idx, file, chain) else {
+                        continue;
+                    };
+                    self.setup_files.push(setup.clone());
+                    chain.push(canonical(&setup));
+                    self.read(&setup, &text, chain);
+                    chain.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Drop the TODO state if it's not one of the TODO keywords of the file, putting the word
+    /// back into the header text.
+    pub fn check_todo(&self, todo: Option<OrgTodo>, rest: String) -> (Option<OrgTodo>, String) {
+        match todo {
+            Some(todo)
+                if !self.todo_keywords.is_empty()
+                    && !self.todo_keywords.iter().any(|x| x == todo.keyword()) =>
+            {
+                let rest = format!("{} {}", todo.keyword(), rest);
+                (None, rest)
+            }
+            todo => (todo, rest),
+        }
+    }
+}
+
+/// Replace the `#+INCLUDE:` lines of the file at `path` with the contents of the included files,
+/// recursively. Every line keeps track of the file and the line it comes from.
+pub fn expand_includes(path: &Path, text: &str) -> SourceText {
+    let mut source = SourceText::default();
+    let lines = text.lines().collect::<Vec<_>>();
+    include(
+        &mut source,
+        path,
+        None,
+        &lines,
+        0,
+        &mut vec![canonical(path)],
+    );
+    source
+}
+
+/// Add `lines` of the file at `path` to `source`. `file_idx` is the index of the file in the
+/// includes of `source`, `None` for the file that is searched. `first_line` is the line in the
+/// file that `lines` start from.
+fn include(
+    source: &mut SourceText,
+    path: &Path,
+    file_idx: Option<usize>,
+    lines: &[&str],
+    first_line: usize,
+    chain: &mut Vec<PathBuf>,
+) {
+    let push = |source: &mut SourceText, line: &str, origin: usize| {
+        source.text.push_str(line);
+        source.text.push('\n');
+        source.origins.push((file_idx, origin));
+    };
+
+    for (idx, line) in lines.iter().enumerate() {
+        let origin = first_line + idx;
+        let Some((_, value)) = keyword(line).filter(|(key, _)| key == "INCLUDE") else {
+            push(source, line, origin);
+            continue;
+        };
+
+        let options = IncludeOptions::parse(value);
+        let Some((included, text)) = open(path, origin, options.file, chain) else {
+            push(source, line, origin);
+            continue;
+        };
+
+        let included_lines = text.lines().collect::<Vec<_>>();
+        let end = options
+            .end
+            .unwrap_or(included_lines.len())
+            .min(included_lines.len());
+        let start = options.start.min(end);
+        let included_lines = &included_lines[start..end];
+
+        source.includes.push(included.clone());
+        let included_idx = Some(source.includes.len() - 1);
+
+        match &options.block {
+            // Content of blocks is taken as is, the wrapping lines point to the #+INCLUDE: line
+            Some(block) => {
+                let name = block.split_whitespace().next().unwrap_or_default();
+                push(source, &format!("#+BEGIN_{}", block), origin);
+                for (offset, x) in included_lines.iter().enumerate() {
+                    source.text.push_str(x);
+                    source.text.push('\n');
+                    source.origins.push((included_idx, start + offset));
+                }
+                push(source, &format!("#+END_{}", name), origin);
+            }
+            None => {
+                chain.push(canonical(&included));
+                include(
+                    source,
+                    &included,
+                    included_idx,
+                    included_lines,
+                    start,
+                    chain,
+                );
+                chain.pop();
+            }
+        }
+    }
+}
+
+/// Options of an `#+INCLUDE:` line, like `"file.org" src python :lines "5-10"`.
+#[derive(Debug, PartialEq, Eq)]
+struct IncludeOptions<'a> {
+    file: &'a str,
+    /// Block to wrap the included lines in, like `src python` or `example`.
+    block: Option<String>,
+    /// Index of the first included line.
+    start: usize,
+    /// Index of the line after the last included line.
+    end: Option<usize>,
+}
+
+impl<'a> IncludeOptions<'a> {
+    fn parse(value: &'a str) -> IncludeOptions<'a> {
+        let value = value.trim();
+        let (file, rest) = match value.strip_prefix('"') {
+            Some(x) => x.split_once('"').unwrap_or((x, "")),
+            None => value.split_once(' ').unwrap_or((value, "")),
+        };
+
+        let mut options = IncludeOptions {
+            file,
+            block: None,
+            start: 0,
+            end: None,
+        };
+
+        let mut words = rest.split_whitespace().peekable();
+        while let Some(word) = words.next() {
+            match word {
+                // Lines are 1-based, the end is excluded
+                ":lines" => {
+                    let range = words.next().map(unquote).unwrap_or_default();
+                    let (start, end) = range.split_once('-').unwrap_or((range, ""));
+                    options.start = start.parse::<usize>().map_or(0, |x| x.saturating_sub(1));
+                    options.end = end.parse::<usize>().ok().map(|x| x.saturating_sub(1));
+                }
+                x if x.starts_with(':') => {
+                    words.next();
+                }
+                "src" | "export" => {
+                    let lang = words.next_if(|x| !x.starts_with(':'));
+                    options.block = Some(format!("{} {}", word.to_uppercase(), lang.unwrap_or("")));
+                }
+                x => options.block = Some(x.to_uppercase()),
+            }
+        }
+
+        options
+    }
+}
+
+/// Split a line like `#+KEY: value` into the uppercase key and the value.
+fn keyword(line: &str) -> Option<(String, &str)> {
+    let line = line.trim_start();
+    if !line.starts_with("#+") {
+        return None;
+    }
+
+    let (key, value) = line[2..].split_once(':')?;
+    let is_key = !key.is_empty() && key.chars().all(|x| x.is_alphanumeric() || x == '_');
+    is_key.then(|| (key.to_uppercase(), value.trim()))
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|x| x.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Open the file that is referred from the line `idx` of the file at `path`. Prints a warning and
+/// returns `None` if the file can't be read or if it's already in `chain`.
+fn open(path: &Path, idx: usize, file: &str, chain: &[PathBuf]) -> Option<(PathBuf, String)> {
+    let base = path.parent().unwrap_or_else(|| Path::new("/"));
+    let target = file_utils::resolve_path(base, file);
+
+    if chain.contains(&canonical(&target)) {
+        eprintln!(
+            "warning: {}:{}: including `{}` would create a cycle",
+            path.display(),
+            idx + 1,
+            file
+        );
+        return None;
+    }
+
+    match std::fs::read(&target) {
+        Ok(bytes) => Some((target, file_utils::decode(&bytes).0)),
+        Err(err) => {
+            eprintln!(
+                "warning: {}:{}: can't read `{}`: {}",
+                path.display(),
+                idx + 1,
+                file,
+                err
+            );
+            None
+        }
+    }
+}
+
+#[test]
+fn test_keyword() {
+    assert_eq!(
+        keyword("#+todo: TODO | DONE"),
+        Some(("TODO".into(), "TODO | DONE"))
+    );
+    assert_eq!(keyword("#+BEGIN_SRC"), None);
+    assert_eq!(keyword("text #+TITLE: x"), None);
+}
+
+#[test]
+fn test_include_options() {
+    assert_eq!(
+        IncludeOptions::parse(r#""code.py" src python :lines "5-10""#),
+        IncludeOptions {
+            file: "code.py",
+            block: Some("SRC python".into()),
+            start: 4,
+            end: Some(9),
+        }
+    );
+    assert_eq!(
+        IncludeOptions::parse("other.org :minlevel 2"),
+        IncludeOptions {
+            file: "other.org",
+            block: None,
+            start: 0,
+            end: None,
+        }
+    );
+}
+
+#[test]
+fn test_file_settings() {
+    use crate::org::header::OrgPriority;
+
+    let settings = FileSettings::collect(
+        Path::new("/nonexistent/x.org"),
+        "#+TODO: NEXT(n) WAIT | DONE\n#+FILETAGS: :work:home:\n#+PRIORITIES: 1 5 3\n",
+    );
+    assert_eq!(
+        settings.priorities.clone().and_then(|x| x.default),
+        Some(OrgPriority("3".into()))
+    );
+    assert_eq!(settings.todo_keywords, vec!["NEXT", "WAIT", "DONE"]);
+    assert_eq!(settings.filetags, vec!["work", "home"]);
+    assert_eq!(
+        settings.check_todo(Some(OrgTodo::Other("NOTE".into())), "x".into()),
+        (None, "NOTE x".into())
+    );
+    assert_eq!(
+        settings.check_todo(Some(OrgTodo::Other("NEXT".into())), "x".into()),
+        (Some(OrgTodo::Other("NEXT".into())), "x".into())
+    );
+}
@@ -0,0 +1,197 @@
+use crate::extensions::StartsWithIgnoreCase;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct OrgTableRow {
+    /// On which line is the row found.
+    pub line: usize,
+    /// Contents of the cells, trimmed.
+    pub cells: Vec<String>,
+}
+
+/// An org or markdown pipe table.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct OrgTable {
+    /// Name of the table, given with `#+NAME:` just before the table.
+    pub name: Option<String>,
+    /// On which line does the table start.
+    pub line: usize,
+    /// Column names. Only tables whose first row is followed by a separator line have a header.
+    pub header: Option<Vec<String>>,
+    /// Rows of the table, excluding the header and the separator lines.
+    pub rows: Vec<OrgTableRow>,
+}
+
+impl OrgTable {
+    /// Find the index of the column with the given name, ignoring case.
+    pub fn column(&self, name: &str) -> Option<usize> {
+        column(self.header.as_ref()?, name)
+    }
+
+    /// Render the table as CSV (or any other delimiter separated format).
+    pub fn to_csv(&self, delimiter: char) -> String {
+        let escape = |x: &String| {
+            if x.contains(delimiter) || x.contains('"') || x.contains('\n') {
+                format!("\"{}\"", x.replace('"', "\"\""))
+            } else {
+                x.clone()
+            }
+        };
+
+        self.header
+            .iter()
+            .chain(self.rows.iter().map(|x| &x.cells))
+            .map(|cells| {
+                let mut line = cells
+                    .iter()
+                    .map(escape)
+                    .collect::<Vec<_>>()
+                    .join(&delimiter.to_string());
+                line.push('\n');
+                line
+            })
+            .collect()
+    }
+}
+
+/// Find the index of the column with the given name in the given header, ignoring case.
+pub fn column(header: &[String], name: &str) -> Option<usize> {
+    header.iter().position(|x| x.eq_ignore_ascii_case(name))
+}
+
+/// Split a table line, like `| a | b |`, into its cells. Returns `None` if the line is not a part
+/// of a table. Separator lines return an empty list.
+pub fn parse_row(line: &str) -> Option<Vec<String>> {
+    let line = line.trim();
+    let inner = line.strip_prefix('|')?;
+
+    if is_separator(line) {
+        return Some(vec![]);
+    }
+
+    let inner = inner.strip_suffix('|').unwrap_or(inner);
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut chars = inner.chars().peekable();
+    while let Some(chr) = chars.next() {
+        match chr {
+            // Markdown allows escaping pipes inside of cells
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            x => cell.push(x),
+        }
+    }
+    cells.push(cell.trim().to_string());
+
+    Some(cells)
+}
+
+/// Is this a separator line, like `|---+---|` in org or `|---|:---:|` in markdown?
+pub fn is_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with('|')
+        && line.contains('-')
+        && line
+            .chars()
+            .all(|x| matches!(x, '|' | '-' | '+' | ':' | ' '))
+}
+
+/// Collects tables while going through a file line by line.
+#[derive(Debug, Default)]
+pub struct TableBuilder {
+    /// Name from the last `#+NAME:` line, if it's directly before the current line.
+    name: Option<String>,
+    current: Option<OrgTable>,
+    pub tables: Vec<OrgTable>,
+}
+
+impl TableBuilder {
+    /// Feed the next line into the builder. `next` is the line that comes after `line`, it's
+    /// used for deciding if a row is a header row.
+    pub fn push(&mut self, idx: usize, line: &str, next: Option<&str>) {
+        let cells = match parse_row(line) {
+            Some(cells) => cells,
+            None => {
+                self.finish();
+
+                // Other keywords, like #+CAPTION:, may come between the name and the table
+                let line = line.trim();
+                if line.starts_with_i("#+NAME:") {
+                    self.name = Some(line[7..].trim().to_string());
+                } else if !line.starts_with("#+") {
+                    self.name = None;
+                }
+                return;
+            }
+        };
+
+        let table = self.current.get_or_insert_with(|| OrgTable {
+            name: self.name.take(),
+            line: idx,
+            header: None,
+            rows: vec![],
+        });
+
+        if cells.is_empty() {
+            return;
+        }
+
+        let is_first_row = table.header.is_none() && table.rows.is_empty();
+        if is_first_row && next.is_some_and(is_separator) {
+            table.header = Some(cells);
+        } else {
+            table.rows.push(OrgTableRow { line: idx, cells });
+        }
+    }
+
+    /// Finish the table that is being built, if there is any.
+    pub fn finish(&mut self) {
+        if let Some(table) = self.current.take() {
+            self.tables.push(table);
+        }
+    }
+
+    /// The table that the last pushed line belongs to.
+    pub fn current(&self) -> Option<&OrgTable> {
+        self.current.as_ref()
+    }
+}
+
+#[test]
+fn test_parse_row() {
+    assert_eq!(
+        parse_row("  | a | b \\| c |  d|"),
+        Some(vec!["a".into(), "b | c".into(), "d".into()])
+    );
+    assert_eq!(parse_row("|---+---|"), Some(vec![]));
+    assert_eq!(parse_row("| :--- | ---: |"), Some(vec![]));
+    assert_eq!(parse_row("not a table"), None);
+}
+
+#[test]
+fn test_table_builder() {
+    let lines = [
+        "#+NAME: people",
+        "| Name | Age |",
+        "|------+-----|",
+        "| Ali  | 30  |",
+        "| \"Bo\", Jr | 4 |",
+        "text",
+    ];
+    let mut builder = TableBuilder::default();
+    for (idx, line) in lines.iter().enumerate() {
+        builder.push(idx, line, lines.get(idx + 1).copied());
+    }
+    builder.finish();
+
+    let table = &builder.tables[0];
+    assert_eq!(table.name, Some("people".into()));
+    assert_eq!(table.column("age"), Some(1));
+    assert_eq!(table.rows.len(), 2);
+    assert_eq!(
+        table.to_csv(','),
+        "Name,Age\nAli,30\n\"\"\"Bo\"\", Jr\",4\n"
+    );
+}
@@ -0,0 +1,70 @@
+use combine::Parser;
+
+use crate::parsers;
+
+/// A dedicated target, `<<target>>`, or a radio target, `<<<target>>>`. Links like `[[target]]`
+/// point to them.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct OrgTarget {
+    /// On which line is the target found.
+    pub line: usize,
+    pub name: String,
+    /// Is this a radio target?
+    pub is_radio: bool,
+}
+
+impl OrgTarget {
+    /// Find all targets in the given line.
+    pub fn parse_all(line: &str, idx: usize) -> Vec<OrgTarget> {
+        let mut targets = vec![];
+        let mut rest = line;
+
+        while let Some(pos) = rest.find("<<") {
+            rest = &rest[pos..];
+            match parsers::org_target().parse(rest) {
+                // Org doesn't allow targets to start or end with a whitespace
+                Ok(((name, is_radio), next)) if name.trim() == name => {
+                    targets.push(OrgTarget {
+                        line: idx,
+                        name,
+                        is_radio,
+                    });
+                    rest = next;
+                }
+                _ => rest = &rest[1..],
+            }
+        }
+
+        targets
+    }
+
+    /// The target as it's written in the document.
+    pub fn raw(&self) -> String {
+        if self.is_radio {
+            format!("<<<{}>>>", self.name)
+        } else {
+            format!("<<{}>>", self.name)
+        }
+    }
+}
+
+#[test]
+fn test_parse_targets() {
+    let targets = OrgTarget::parse_all("See <<here>>, <<<radio>>> and << not >> a <b>", 2);
+    assert_eq!(
+        targets,
+        vec![
+            OrgTarget {
+                line: 2,
+                name: "here".into(),
+                is_radio: false,
+            },
+            OrgTarget {
+                line: 2,
+                name: "radio".into(),
+                is_radio: true,
+            },
+        ]
+    );
+    assert_eq!(targets[1].raw(), "<<<radio>>>");
+}
@@ -138,6 +138,92 @@ where
         .map(|(key, _, val)| (key, val))
 }
 
+/// Parse `[[target][description]]` or `[[target]]`. Also matches wiki-style `[[Note Name]]` links.
+pub fn org_link<Input>() -> impl Parser<Input, Output = (String, Option<String>)>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        token('['),
+        token('['),
+        many1(satisfy(|x| x != ']' && x != '[')),
+        token(']'),
+        optional(between(token('['), token(']'), many1(satisfy(|x| x != ']')))),
+        token(']'),
+    )
+        .map(|(_, _, target, _, description, _)| (target, description))
+}
+
+/// Parse `[description](target)`. Optional link titles, like `(target "title")`, are dropped.
+pub fn md_link<Input>() -> impl Parser<Input, Output = (String, Option<String>)>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        between(token('['), token(']'), many(satisfy(|x| x != ']' && x != '['))),
+        between(token('('), token(')'), many1(satisfy(|x| x != ')'))),
+    )
+        .map(|(description, target): (String, String)| {
+            let target = target.trim();
+            let target = match target.strip_prefix('<') {
+                Some(x) => x.split('>').next().unwrap_or(x),
+                None => target.split_whitespace().next().unwrap_or(target),
+            };
+            let description = Some(description).filter(|x| !x.is_empty());
+            (target.to_string(), description)
+        })
+}
+
+/// Parse `[fn:name]`, `[fn:name:inline definition]` or `[fn::anonymous definition]`.
+pub fn org_footnote<Input>() -> impl Parser<Input, Output = (String, Option<String>)>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        attempt(string("[fn:")),
+        many(satisfy(|x: char| x.is_alphanumeric() || x == '-' || x == '_')),
+        optional((token(':'), many1(satisfy(|x| x != ']'))).map(|(_, text)| text)),
+        token(']'),
+    )
+        .map(|(_, name, definition, _)| (name, definition))
+}
+
+/// Parse `[^name]`.
+pub fn md_footnote<Input>() -> impl Parser<Input, Output = (String, Option<String>)>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        attempt(string("[^")),
+        many1(satisfy(|x| x != ']' && x != ' ')),
+        token(']'),
+    )
+        .map(|(_, name, _)| (name, None))
+}
+
+/// Parse a dedicated target, `<<target>>`, or a radio target, `<<<target>>>`. Returns the name
+/// of the target and whether it's a radio target.
+pub fn org_target<Input>() -> impl Parser<Input, Output = (String, bool)>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        attempt(string("<<")),
+        optional(token('<')),
+        many1(satisfy(|x| x != '<' && x != '>')),
+        string(">>"),
+        optional(token('>')),
+    )
+        .map(|(_, open, name, _, close): (_, _, String, _, _)| {
+            (name, open.is_some() && close.is_some())
+        })
+}
+
 #[test]
 fn test_hour() {
     assert_eq!(hour().parse("13:27").unwrap().0, (13, 27));
@@ -266,3 +352,28 @@ fn test_org_todo() {
         )
     );
 }
+
+#[test]
+fn test_org_link() {
+    assert_eq!(
+        org_link().parse("[[file:x.org::*Heading][desc]] rest").unwrap(),
+        (("file:x.org::*Heading".into(), Some("desc".into())), " rest")
+    );
+    assert_eq!(
+        org_link().parse("[[Note Name]]").unwrap(),
+        (("Note Name".into(), None), "")
+    );
+    assert!(org_link().parse("[not a link]").is_err());
+}
+
+#[test]
+fn test_md_link() {
+    assert_eq!(
+        md_link().parse("[text](path.md#anchor) rest").unwrap(),
+        (("path.md#anchor".into(), Some("text".into())), " rest")
+    );
+    assert_eq!(
+        md_link().parse("[](<with space.md> \"title\")").unwrap(),
+        (("with space.md".into(), None), "")
+    );
+}
@@ -0,0 +1,135 @@
+//! A quick check of the whole text of a file, which skips the files that can't match the query
+//! before any of their lines are parsed.
+
+use aho_corasick::AhoCorasick;
+use regex::Regex;
+use regex_syntax::hir::literal::Extractor;
+
+use crate::query::Query;
+
+/// Looks for the words that every match of the must terms and the regexes of the query contains.
+///
+/// Units are matched after their inline markup is stripped, which removes the markers around the
+/// words but never splits or joins the words themselves, so the words of the terms appear in the
+/// text of the file as they are. Links are the exception, as a link is replaced with its
+/// description even when it's right next to a word.
+pub struct Prefilter {
+    /// Every word that is looked for.
+    automaton: AhoCorasick,
+    /// Words of the must terms, by their indices in the automaton.
+    musts: Vec<usize>,
+    /// Words of the prefixes that the matches of each regex may start with. One of the prefixes
+    /// must appear with all of its words.
+    regexes: Vec<Vec<Vec<usize>>>,
+    /// Links that are joined with a word, like `word[[target]]`.
+    joined_link: Regex,
+}
+
+impl Prefilter {
+    /// Create the prefilter of the query, or `None` if there is nothing to look for, like when the
+    /// query only has fuzzy terms.
+    pub fn new(query: &Query) -> Option<Prefilter> {
+        let mut patterns: Vec<&str> = vec![];
+        let mut index_of = |word| match patterns.iter().position(|x| *x == word) {
+            Some(idx) => idx,
+            None => {
+                patterns.push(word);
+                patterns.len() - 1
+            }
+        };
+
+        let musts = query
+            .musts
+            .iter()
+            .flat_map(|x| words(x))
+            .map(&mut index_of)
+            .collect::<Vec<_>>();
+
+        let prefixes = query
+            .regexes
+            .iter()
+            .filter_map(prefixes)
+            .collect::<Vec<_>>();
+        let regexes = prefixes
+            .iter()
+            .map(|x| {
+                x.iter()
+                    .map(|prefix| words(prefix).map(&mut index_of).collect::<Vec<_>>())
+                    .collect::<Vec<_>>()
+            })
+            // A prefix without any words doesn't rule out any file
+            .filter(|x| !x.iter().any(Vec::is_empty))
+            .collect::<Vec<_>>();
+
+        if musts.is_empty() && regexes.is_empty() {
+            return None;
+        }
+
+        Some(Prefilter {
+            automaton: AhoCorasick::new(&patterns).ok()?,
+            musts,
+            regexes,
+            joined_link: Regex::new(r"[\p{Alphabetic}\p{N}]!?\[|[\])][\p{Alphabetic}\p{N}]")
+                .ok()?,
+        })
+    }
+
+    /// Can any unit of the text match the query?
+    pub fn may_match(&self, text: &str) -> bool {
+        let mut found = vec![false; self.automaton.patterns_len()];
+        let mut missing = found.len();
+        for word in self.automaton.find_overlapping_iter(text) {
+            let idx = word.pattern().as_usize();
+            if !found[idx] {
+                found[idx] = true;
+                missing -= 1;
+                if missing == 0 {
+                    break;
+                }
+            }
+        }
+
+        let has_all = |words: &Vec<usize>| words.iter().all(|x| found[*x]);
+        (has_all(&self.musts) && self.regexes.iter().all(|x| x.iter().any(has_all)))
+            || self.joined_link.is_match(text)
+    }
+}
+
+/// Runs of letters and digits in the term.
+fn words(term: &str) -> impl Iterator<Item = &str> {
+    term.split(|x: char| !x.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+}
+
+/// Prefixes that every match of the regex starts with one of, or `None` if there are too many of
+/// them to look for.
+fn prefixes(regex: &Regex) -> Option<Vec<String>> {
+    let hir = regex_syntax::parse(regex.as_str()).ok()?;
+    let prefixes = Extractor::new().extract(&hir);
+    // Long prefixes may be cut in the middle of a character, which just ends their last word early
+    let prefixes = prefixes
+        .literals()?
+        .iter()
+        .map(|x| String::from_utf8_lossy(x.as_bytes()).into_owned())
+        .collect();
+    Some(prefixes)
+}
+
+#[test]
+fn test_prefilter() {
+    let query = |musts: &[&str], regexes: &[&str]| Query {
+        musts: musts.iter().map(|x| x.to_string()).collect(),
+        regexes: regexes.iter().map(|x| Regex::new(x).unwrap()).collect(),
+        ..Default::default()
+    };
+
+    assert!(Prefilter::new(&query(&[], &[".*"])).is_none());
+
+    let prefilter = Prefilter::new(&query(&["fast rust"], &["(foo|bar)\\d+"])).unwrap();
+    assert!(prefilter.may_match("* Rust\n*fast* rust, foo1"));
+    assert!(prefilter.may_match("rust fast bar"));
+    assert!(!prefilter.may_match("fast rust baz1"));
+    assert!(!prefilter.may_match("fast Rust foo2"));
+    // The description of the link may be joined with `fast`
+    assert!(prefilter.may_match("fast[[file:x.org][rust]]"));
+}
@@ -1,18 +1,25 @@
 use regex::Regex;
 
-use combine::parser::char::{char, spaces};
+use combine::parser::char::{char, spaces, string};
 use combine::stream::easy::ParseError;
-use combine::{between, choice, many1, satisfy, sep_by, EasyParser, Parser};
+use combine::{attempt, between, choice, many, many1, satisfy, sep_by, EasyParser, Parser};
 
 #[derive(Debug)]
 pub enum QueryToken {
     Regex(Regex),
     Must(String),
     None(String),
+    Link(String),
+    Column(String, Option<String>),
+    Drawer(String),
+    Lang(String),
+    Tangle(String),
+    Block(String),
+    BlockArg(String, Option<String>),
     Plain(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Query {
     /// Query string that user provided.
     pub full: String,
@@ -22,6 +29,20 @@ pub struct Query {
     pub nones: Vec<String>,
     /// `(some|regex)`
     pub regexes: Vec<Regex>,
+    /// link:target
+    pub links: Vec<String>,
+    /// col:Name=value, value is optional. `col:Name` matches rows where the column is not empty.
+    pub cols: Vec<(String, Option<String>)>,
+    /// drawer:NAME
+    pub drawers: Vec<String>,
+    /// lang:python
+    pub langs: Vec<String>,
+    /// tangle:file, file is optional. `tangle:` matches blocks that are tangled anywhere.
+    pub tangles: Vec<String>,
+    /// block:name
+    pub blocks: Vec<String>,
+    /// arg:key=value, value is optional. `arg:key` matches blocks that have the argument.
+    pub block_args: Vec<(String, Option<String>)>,
     /// full - (musts + nones + regexes). Used for fuzzy searching.
     pub rest: Vec<String>,
 }
@@ -33,6 +54,13 @@ impl PartialEq for Query {
             && self.musts == other.musts
             && self.nones == other.nones
             && self.regexes.iter().zip(other.regexes.iter()).all(|(x, y)| x.as_str() == y.as_str())
+            && self.links == other.links
+            && self.cols == other.cols
+            && self.drawers == other.drawers
+            && self.langs == other.langs
+            && self.tangles == other.tangles
+            && self.blocks == other.blocks
+            && self.block_args == other.block_args
             && self.rest == other.rest
     }
 }
@@ -45,6 +73,13 @@ impl Query {
         let mut musts: Vec<String> = vec![];
         let mut nones = vec![];
         let mut regexes = vec![];
+        let mut links = vec![];
+        let mut cols = vec![];
+        let mut drawers = vec![];
+        let mut langs = vec![];
+        let mut tangles = vec![];
+        let mut blocks = vec![];
+        let mut block_args = vec![];
         let mut rest = vec![];
 
         let non_ws = satisfy(|x| x != ' ');
@@ -56,6 +91,19 @@ impl Query {
             between(char('`'), char('`'), many1(non_backtick))
                 .map(|x: String| QueryToken::Regex(Regex::new(&x).unwrap())),
             (char('-'), many1(non_ws)).map(|x| QueryToken::None(x.1)),
+            (attempt(string("link:")), many1(non_ws)).map(|x| QueryToken::Link(x.1)),
+            (attempt(string("col:")), many1(non_ws)).map(|(_, x): (_, String)| match x.split_once('=') {
+                Some((name, value)) => QueryToken::Column(name.into(), Some(value.into())),
+                None => QueryToken::Column(x, None),
+            }),
+            (attempt(string("drawer:")), many1(non_ws)).map(|x| QueryToken::Drawer(x.1)),
+            (attempt(string("lang:")), many1(non_ws)).map(|x| QueryToken::Lang(x.1)),
+            (attempt(string("tangle:")), many(non_ws)).map(|x| QueryToken::Tangle(x.1)),
+            (attempt(string("block:")), many1(non_ws)).map(|x| QueryToken::Block(x.1)),
+            (attempt(string("arg:")), many1(non_ws)).map(|(_, x): (_, String)| match x.split_once('=') {
+                Some((key, value)) => QueryToken::BlockArg(key.into(), Some(value.into())),
+                None => QueryToken::BlockArg(x, None),
+            }),
             many1(non_ws).map(|x| QueryToken::Plain(x)),
         ));
         let mut query = sep_by(token, spaces());
@@ -66,6 +114,13 @@ impl Query {
             QueryToken::Plain(r) => rest.push(r),
             QueryToken::Must(r) => musts.push(r),
             QueryToken::None(r) => nones.push(r),
+            QueryToken::Link(r) => links.push(r),
+            QueryToken::Column(name, value) => cols.push((name, value)),
+            QueryToken::Drawer(r) => drawers.push(r),
+            QueryToken::Lang(r) => langs.push(r),
+            QueryToken::Tangle(r) => tangles.push(r),
+            QueryToken::Block(r) => blocks.push(r),
+            QueryToken::BlockArg(key, value) => block_args.push((key, value)),
         });
 
         Ok(Query {
@@ -73,11 +128,28 @@ impl Query {
             musts,
             nones,
             regexes,
+            links,
+            cols,
+            drawers,
+            langs,
+            tangles,
+            blocks,
+            block_args,
             rest,
         })
     }
 }
 
+impl Query {
+    /// Does the query have terms that only match lines inside of blocks, like lang:python?
+    pub fn has_block_terms(&self) -> bool {
+        !self.langs.is_empty()
+            || !self.tangles.is_empty()
+            || !self.blocks.is_empty()
+            || !self.block_args.is_empty()
+    }
+}
+
 impl Default for Query {
     fn default() -> Self {
         Query {
@@ -85,6 +157,13 @@ impl Default for Query {
             musts: vec![],
             nones: vec![],
             regexes: vec![],
+            links: vec![],
+            cols: vec![],
+            drawers: vec![],
+            langs: vec![],
+            tangles: vec![],
+            blocks: vec![],
+            block_args: vec![],
             rest: vec![],
         }
     }
@@ -102,3 +181,44 @@ fn test_parse_query() {
         ..Default::default()
     });
 }
+
+#[test]
+fn test_parse_link_query() {
+    assert_eq!(Query::new("link:x.org notes").unwrap(), Query {
+        full: "link:x.org notes".into(),
+        links: vec!["x.org".into()],
+        rest: vec!["notes".into()],
+        ..Default::default()
+    });
+}
+
+#[test]
+fn test_parse_column_query() {
+    assert_eq!(Query::new("col:Status=done col:Owner").unwrap(), Query {
+        full: "col:Status=done col:Owner".into(),
+        cols: vec![("Status".into(), Some("done".into())), ("Owner".into(), None)],
+        ..Default::default()
+    });
+}
+
+#[test]
+fn test_parse_drawer_query() {
+    assert_eq!(Query::new("drawer:LOGBOOK clock").unwrap(), Query {
+        full: "drawer:LOGBOOK clock".into(),
+        drawers: vec!["LOGBOOK".into()],
+        rest: vec!["clock".into()],
+        ..Default::default()
+    });
+}
+
+#[test]
+fn test_parse_block_query() {
+    assert_eq!(Query::new("lang:sh arg:dir=/ssh: tangle: block:build").unwrap(), Query {
+        full: "lang:sh arg:dir=/ssh: tangle: block:build".into(),
+        langs: vec!["sh".into()],
+        tangles: vec!["".into()],
+        blocks: vec!["build".into()],
+        block_args: vec![("dir".into(), Some("/ssh:".into()))],
+        ..Default::default()
+    });
+}
@@ -0,0 +1,158 @@
+//! BM25 ranking of the results, see `--rank bm25`. Sections, a header and the lines until the next
+//! header, are the documents that are ranked. Words in the header count more than the words in
+//! the body, and the results get the score of the section they are in.
+
+use crate::markup;
+use crate::query::Query;
+
+/// How much a word in the header of a section counts, compared to a word in its body.
+pub const HEADER_WEIGHT: f64 = 2.0;
+
+/// Saturation of the term frequencies.
+const K1: f64 = 1.2;
+
+/// How much the length of a section matters.
+const B: f64 = 0.75;
+
+/// Words of the query that are ranked, from the fuzzy and the quoted terms.
+pub fn query_terms(query: &Query) -> Vec<String> {
+    let mut terms = query
+        .rest
+        .iter()
+        .chain(query.musts.iter())
+        .flat_map(|x| markup::words(x))
+        .collect::<Vec<_>>();
+    terms.sort_unstable();
+    terms.dedup();
+    terms
+}
+
+/// How often the query terms appear in a section.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SectionStats {
+    /// Weighted frequency of each query term, in the order of the terms.
+    pub freqs: Vec<f64>,
+    /// Weighted number of words in the section.
+    pub length: f64,
+}
+
+impl SectionStats {
+    pub fn new(terms: &[String]) -> SectionStats {
+        SectionStats {
+            freqs: vec![0.0; terms.len()],
+            length: 0.0,
+        }
+    }
+
+    /// Count the words of the given text, which is a part of the section, with the given weight.
+    pub fn add(&mut self, terms: &[String], text: &str, weight: f64) {
+        for word in markup::words(text) {
+            if let Ok(idx) = terms.binary_search(&word) {
+                self.freqs[idx] += weight;
+            }
+            self.length += weight;
+        }
+    }
+}
+
+/// Statistics of all sections in the corpus, for the query terms.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CorpusStats {
+    pub sections: usize,
+    /// Sum of the weighted lengths of the sections.
+    pub length: f64,
+    /// Number of sections that contain each query term.
+    pub freqs: Vec<usize>,
+}
+
+impl CorpusStats {
+    pub fn new(terms: &[String]) -> CorpusStats {
+        CorpusStats {
+            sections: 0,
+            length: 0.0,
+            freqs: vec![0; terms.len()],
+        }
+    }
+
+    /// Count the section in the corpus. Sections without any words, like an empty text before
+    /// the first header, are not counted.
+    pub fn add(&mut self, section: &SectionStats) {
+        if section.length == 0.0 {
+            return;
+        }
+
+        self.sections += 1;
+        self.length += section.length;
+        for (total, freq) in self.freqs.iter_mut().zip(section.freqs.iter()) {
+            *total += usize::from(*freq > 0.0);
+        }
+    }
+
+    /// Combine the statistics of two parts of the corpus.
+    pub fn merge(mut self, other: &CorpusStats) -> CorpusStats {
+        self.sections += other.sections;
+        self.length += other.length;
+        for (total, freq) in self.freqs.iter_mut().zip(other.freqs.iter()) {
+            *total += freq;
+        }
+        self
+    }
+
+    /// BM25 score of the section.
+    pub fn score(&self, section: &SectionStats) -> f64 {
+        self.term_scores(section).iter().sum()
+    }
+
+    /// What each query term adds to the BM25 score of the section, in the order of the terms.
+    pub fn term_scores(&self, section: &SectionStats) -> Vec<f64> {
+        let sections = self.sections.max(1) as f64;
+        let avg_length = (self.length / sections).max(1.0);
+
+        self.freqs
+            .iter()
+            .zip(section.freqs.iter())
+            .map(|(docs, freq)| {
+                if *freq == 0.0 {
+                    return 0.0;
+                }
+                let docs = *docs as f64;
+                let idf = (1.0 + (sections - docs + 0.5) / (docs + 0.5)).ln();
+                let norm = 1.0 - B + B * section.length / avg_length;
+                idf * freq * (K1 + 1.0) / (freq + K1 * norm)
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_bm25() {
+    // Terms are sorted
+    let terms = vec!["common".to_string(), "rare".to_string()];
+
+    let mut corpus = CorpusStats::new(&terms);
+    let mut sections = vec![];
+    for text in [
+        "common rare",
+        "common",
+        "common words",
+        "common other words",
+    ] {
+        let mut section = SectionStats::new(&terms);
+        section.add(&terms, text, 1.0);
+        corpus.add(&section);
+        sections.push(section);
+    }
+    assert_eq!(corpus.sections, 4);
+    assert_eq!(corpus.freqs, vec![4, 1]);
+
+    // Rare terms weigh more than the common ones
+    assert!(corpus.score(&sections[0]) > corpus.score(&sections[1]));
+
+    // Words in the header count more
+    let mut header = SectionStats::new(&terms);
+    header.add(&terms, "rare", HEADER_WEIGHT);
+    let mut body = SectionStats::new(&terms);
+    body.add(&terms, "rare", 1.0);
+    body.add(&terms, "filler", 1.0);
+    assert!(corpus.score(&header) > corpus.score(&body));
+}
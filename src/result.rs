@@ -1,21 +1,46 @@
 use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
 
-use crate::{args::Args, org::header::OrgHeader};
+use crate::{
+    args::{Args, Rank},
+    boost::{Signals, Weights},
+    explain::Explanation,
+    org::header::OrgHeader,
+    rank::SectionStats,
+};
 
 #[derive(Debug)]
 pub struct SearchResult<'a> {
     /// Score.
     pub score: i64,
-    /// Line number.
+    /// Line number. For paragraph and section units, this is the first line of the unit.
     pub line: usize,
+    /// How many lines does the matched unit span?
+    pub span: usize,
+    /// Number of the notebook cell that the line is in, starting from 1. For notebooks, `line` is
+    /// the line in the cell.
+    pub cell: Option<usize>,
     /// In which file?
     pub file_path: String,
     /// List of headers that this belongs to.
-    pub headers: Vec<OrgHeader<'a>>,
+    pub headers: Arc<[OrgHeader<'a>]>,
     /// Full line content itself.
     pub content: String,
+    /// Names and definitions of the footnotes referenced in the content. Only collected with
+    /// --footnotes.
+    pub footnotes: Vec<(String, String)>,
     /// Is this a header line?
     pub is_header: bool,
+    /// How often the query terms appear in the section of the result. Only collected with
+    /// --rank bm25.
+    pub section: Option<SectionStats>,
+    /// Score before the boosts.
+    pub base_score: i64,
+    /// What the boosts of the score are computed from.
+    pub signals: Signals,
+    /// Why the result matched. Only collected with --explain.
+    pub explanation: Option<Explanation>,
     pub args: &'a Args,
 }
 
@@ -23,6 +48,33 @@ pub struct SearchResult<'a> {
 // TODO: Print JSON?
 
 impl<'a> SearchResult<'a> {
+    /// A result at the given line, starting from 1, that didn't come from a search, like the
+    /// results of the subcommands. It has no score, footnotes or explanation.
+    pub fn at(
+        args: &'a Args,
+        path: &Path,
+        line: usize,
+        headers: Vec<OrgHeader<'a>>,
+        content: String,
+    ) -> SearchResult<'a> {
+        SearchResult {
+            score: 0,
+            line,
+            span: 1,
+            cell: None,
+            file_path: path.to_string_lossy().to_string(),
+            headers: headers.into(),
+            content,
+            footnotes: vec![],
+            is_header: false,
+            section: None,
+            base_score: 0,
+            signals: Signals::default(),
+            explanation: None,
+            args,
+        }
+    }
+
     #[allow(unused_must_use)]
     pub fn print(&self) {
         if self.args.no_color {
@@ -41,6 +93,12 @@ impl<'a> SearchResult<'a> {
         };
 
         t.fg(term::color::GREEN).unwrap();
+        if let Some(cell) = self.cell {
+            write!(t, "{}", cell).unwrap();
+            t.fg(term::color::WHITE).unwrap();
+            write!(t, ":").unwrap();
+            t.fg(term::color::GREEN).unwrap();
+        }
         write!(t, "{}", self.line).unwrap();
 
         t.fg(term::color::WHITE).unwrap();
@@ -70,6 +128,56 @@ impl<'a> SearchResult<'a> {
         }
 
         writeln!(t);
+
+        for (name, definition) in &self.footnotes {
+            t.fg(term::color::YELLOW).unwrap();
+            write!(t, "    [fn:{}]", name).unwrap();
+            t.reset().unwrap();
+            writeln!(t, " {}", definition).unwrap();
+        }
+
+        if self.args.is_explaining() {
+            for (label, line) in self.explain() {
+                t.fg(term::color::CYAN).unwrap();
+                write!(t, "    [{}]", label).unwrap();
+                t.reset().unwrap();
+                writeln!(t, " {}", line).unwrap();
+            }
+        }
+    }
+
+    /// Lines that explain the result, each with its label.
+    pub fn explain(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![("score", self.explain_score())];
+        if let Some(explanation) = &self.explanation {
+            lines.extend(explanation.lines());
+        }
+        lines
+    }
+
+    /// Describe how the score is calculated, like `1500 = 1000 fuzzy x 1.50 (header +1.00)`.
+    pub fn explain_score(&self) -> String {
+        let bonuses = self.signals.bonuses(&Weights::from_args(self.args));
+        let ranking = match self.args.rank {
+            Rank::Fuzzy => "fuzzy",
+            Rank::Bm25 => "bm25",
+        };
+
+        let mut explanation = format!(
+            "{} = {} {} x {:.2}",
+            self.score,
+            self.base_score,
+            ranking,
+            1.0 + bonuses.iter().map(|(_, x)| x).sum::<f64>()
+        );
+        if !bonuses.is_empty() {
+            let bonuses = bonuses
+                .iter()
+                .map(|(name, bonus)| format!("{} +{:.2}", name, bonus))
+                .collect::<Vec<_>>();
+            explanation.push_str(&format!(" ({})", bonuses.join(", ")));
+        }
+        explanation
     }
 }
 
@@ -82,7 +190,11 @@ impl fmt::Display for SearchResult<'_> {
         } else {
             ':'
         };
-        write!(f, "{}{}{}", &self.file_path, file_and_line_sep, &self.line);
+        write!(f, "{}{}", &self.file_path, file_and_line_sep);
+        if let Some(cell) = self.cell {
+            write!(f, "{}:", cell);
+        }
+        write!(f, "{}", &self.line);
         if !self.args.no_headers {
             let mut sep = ":";
             for header in self.headers.iter() {
@@ -91,6 +203,18 @@ impl fmt::Display for SearchResult<'_> {
                 write!(f, "{}", header.content).unwrap();
             }
         }
-        write!(f, ":{}", &self.content)
+        write!(f, ":{}", &self.content)?;
+
+        for (name, definition) in &self.footnotes {
+            write!(f, "\n    [fn:{}] {}", name, definition)?;
+        }
+
+        if self.args.is_explaining() {
+            for (label, line) in self.explain() {
+                write!(f, "\n    [{}] {}", label, line)?;
+            }
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,243 @@
+//! Orders of the results, see `--sort`. Results that are equal by the sort key are always ordered
+//! by their path and then by their line, so the output doesn't depend on the order in which the
+//! files are searched.
+
+use std::cell::RefCell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+
+use crate::args::{Args, SortKey};
+use crate::org::datetime::OrgDatePlan;
+use crate::org::header::OrgTodo;
+use crate::result::SearchResult;
+
+/// Orders the results by `--sort` and `--reverse`.
+pub struct Sorter {
+    key: SortKey,
+    reverse: bool,
+    /// Modification times of the files, read once per file instead of once per comparison. Only
+    /// read with --sort mtime.
+    mtimes: RefCell<HashMap<String, Option<SystemTime>>>,
+}
+
+impl Sorter {
+    pub fn new(args: &Args) -> Sorter {
+        Sorter {
+            key: args.sort,
+            reverse: args.reverse,
+            mtimes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Sort the results.
+    pub fn sort(&self, results: &mut [SearchResult]) {
+        results.sort_by(|a, b| self.compare(a, b));
+    }
+
+    /// The first `count` results in order, keeping at most `count` results in memory at once.
+    pub fn top<'a>(
+        &self,
+        results: impl Iterator<Item = SearchResult<'a>>,
+        count: usize,
+    ) -> Vec<SearchResult<'a>> {
+        // The last of the kept results is on the top of the heap, so it's dropped first
+        let mut heap = BinaryHeap::with_capacity(count + 1);
+        for result in results {
+            heap.push(Ranked {
+                result,
+                sorter: self,
+            });
+            if heap.len() > count {
+                heap.pop();
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|x| x.result)
+            .collect()
+    }
+
+    /// Compare two results, the ones that come first are less. With --sort none, every result is
+    /// equal to another.
+    pub fn compare(&self, a: &SearchResult, b: &SearchResult) -> Ordering {
+        if self.key == SortKey::None {
+            return Ordering::Equal;
+        }
+
+        let ordering = self.compare_key(a, b);
+        let ordering = if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        ordering
+            .then_with(|| a.file_path.cmp(&b.file_path))
+            .then_with(|| a.cell.cmp(&b.cell))
+            .then_with(|| a.line.cmp(&b.line))
+    }
+
+    fn mtime(&self, file: &str) -> Option<SystemTime> {
+        *self
+            .mtimes
+            .borrow_mut()
+            .entry(file.to_string())
+            .or_insert_with(|| std::fs::metadata(file).and_then(|x| x.modified()).ok())
+    }
+
+    /// Compare two results by the sort key. Results come first when they have a higher score, a
+    /// newer file, an earlier date, a higher priority or a TODO state that is not done yet.
+    /// Results without a date, a priority or a TODO state come last.
+    fn compare_key(&self, a: &SearchResult, b: &SearchResult) -> Ordering {
+        match self.key {
+            SortKey::None => Ordering::Equal,
+            SortKey::Score => b.score.cmp(&a.score),
+            SortKey::Path => a.file_path.cmp(&b.file_path),
+            SortKey::Line => a.line.cmp(&b.line),
+            SortKey::Mtime => last_if_missing(
+                self.mtime(&a.file_path).map(Reverse),
+                self.mtime(&b.file_path).map(Reverse),
+            ),
+            SortKey::Scheduled => last_if_missing(
+                date(a, OrgDatePlan::Scheduled),
+                date(b, OrgDatePlan::Scheduled),
+            ),
+            SortKey::Deadline => last_if_missing(
+                date(a, OrgDatePlan::Deadline),
+                date(b, OrgDatePlan::Deadline),
+            ),
+            // The priority signal is 1.0 for the highest priority of the file
+            SortKey::Priority => last_if_missing(
+                a.signals.priority.map(|x| Reverse(Importance(x))),
+                b.signals.priority.map(|x| Reverse(Importance(x))),
+            ),
+            SortKey::Todo => todo_rank(a).cmp(&todo_rank(b)),
+        }
+    }
+}
+
+/// A result in the heap of [`Sorter::top`], ordered by the sorter.
+struct Ranked<'a, 's> {
+    result: SearchResult<'a>,
+    sorter: &'s Sorter,
+}
+
+impl PartialEq for Ranked<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked<'_, '_> {}
+
+impl PartialOrd for Ranked<'_, '_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked<'_, '_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorter.compare(&self.result, &other.result)
+    }
+}
+
+/// Compare the values, putting the missing ones last.
+fn last_if_missing<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Date of the given kind in the section of the result.
+fn date(result: &SearchResult, plan: OrgDatePlan) -> Option<DateTime<Utc>> {
+    let datetime = result.headers.last()?.datetime.as_ref()?;
+    (datetime.date_plan == plan).then_some(datetime.date_start)
+}
+
+/// Where the TODO state of the result's section comes in the order: TODO, other keywords by
+/// their names, DONE and no TODO state.
+fn todo_rank<'a>(result: &'a SearchResult) -> (u8, &'a str) {
+    match result.headers.last().and_then(|x| x.todo.as_ref()) {
+        Some(OrgTodo::TODO) => (0, ""),
+        Some(OrgTodo::Other(x)) => (1, x),
+        Some(OrgTodo::DONE) => (2, ""),
+        None => (3, ""),
+    }
+}
+
+/// Priority signals are between 0.0 and 1.0, never NaN, so they can be totally ordered.
+#[derive(PartialEq)]
+struct Importance(f64);
+
+impl Eq for Importance {}
+
+impl PartialOrd for Importance {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Importance {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[test]
+fn test_last_if_missing() {
+    assert_eq!(last_if_missing(Some(1), Some(2)), Ordering::Less);
+    assert_eq!(last_if_missing(Some(2), None), Ordering::Less);
+    assert_eq!(last_if_missing(None, Some(1)), Ordering::Greater);
+    assert_eq!(last_if_missing::<i32>(None, None), Ordering::Equal);
+}
+
+#[test]
+fn test_top() {
+    use std::path::Path;
+
+    let args = Args::parse_from(["marks", "x"]);
+    let sorter = Sorter::new(&args);
+    // Scores with ties, in an order that is neither sorted by the score nor by the path
+    let specs = [
+        (3, "b.org", 4),
+        (5, "a.org", 9),
+        (3, "a.org", 7),
+        (5, "a.org", 2),
+        (3, "b.org", 1),
+    ];
+    let results = || {
+        specs.iter().map(|(score, path, line)| SearchResult {
+            score: *score,
+            ..SearchResult::at(&args, Path::new(path), *line, vec![], String::new())
+        })
+    };
+    let key = |x: &SearchResult| (x.score, x.file_path.clone(), x.line);
+
+    let mut sorted = results().collect::<Vec<_>>();
+    sorter.sort(&mut sorted);
+    assert_eq!(
+        sorted.iter().map(key).collect::<Vec<_>>(),
+        vec![
+            (5, "a.org".into(), 2),
+            (5, "a.org".into(), 9),
+            (3, "a.org".into(), 7),
+            (3, "b.org".into(), 1),
+            (3, "b.org".into(), 4),
+        ]
+    );
+
+    for count in 0..=specs.len() + 1 {
+        let top = sorter.top(results(), count);
+        assert_eq!(
+            top.iter().map(key).collect::<Vec<_>>(),
+            sorted.iter().take(count).map(key).collect::<Vec<_>>()
+        );
+    }
+}
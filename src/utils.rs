@@ -1,12 +1,93 @@
 pub mod file_utils {
-    use walkdir::DirEntry;
-
-    /// Returns if the file starts with dot (".") or not.
-    pub fn is_hidden(entry: &DirEntry) -> bool {
-        entry
-            .file_name()
-            .to_str()
-            .map(|s| s.starts_with("."))
-            .unwrap_or(false)
+    use std::path::{Component, Path, PathBuf};
+
+    /// Resolve `path` relative to `base`, expanding `~/` and removing `.` and `..` components
+    /// without touching the file system.
+    pub fn resolve_path(base: &Path, path: &str) -> PathBuf {
+        let path = match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+            _ => base.join(path),
+        };
+
+        let mut result = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    result.pop();
+                }
+                x => result.push(x),
+            }
+        }
+        result
+    }
+
+    /// Decode the contents of a file. UTF-8 and UTF-16 files with a byte order mark are decoded
+    /// accordingly, other files are decoded as UTF-8 and if that fails, as Latin-1. Returns the text
+    /// and a warning if the file could not be decoded cleanly.
+    pub fn decode(bytes: &[u8]) -> (String, Option<&'static str>) {
+        if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+            return match std::str::from_utf8(rest) {
+                Ok(text) => (text.to_string(), None),
+                Err(_) => (
+                    String::from_utf8_lossy(rest).into_owned(),
+                    Some("invalid UTF-8, undecodable bytes are replaced"),
+                ),
+            };
+        }
+
+        let utf16 = match bytes {
+            [0xFF, 0xFE, rest @ ..] => Some((rest, u16::from_le_bytes as fn([u8; 2]) -> u16)),
+            [0xFE, 0xFF, rest @ ..] => Some((rest, u16::from_be_bytes as fn([u8; 2]) -> u16)),
+            _ => None,
+        };
+        if let Some((rest, from_bytes)) = utf16 {
+            let units = rest.chunks_exact(2).map(|x| from_bytes([x[0], x[1]]));
+            let mut is_lossy = rest.len() % 2 != 0;
+            let text = char::decode_utf16(units)
+                .map(|x| {
+                    x.unwrap_or_else(|_| {
+                        is_lossy = true;
+                        char::REPLACEMENT_CHARACTER
+                    })
+                })
+                .collect();
+            let warning = is_lossy.then_some("invalid UTF-16, undecodable bytes are replaced");
+            return (text, warning);
+        }
+
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), None),
+            Err(_) => (
+                bytes.iter().map(|x| char::from(*x)).collect(),
+                Some("not valid UTF-8, decoded as Latin-1"),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"\xEF\xBB\xBFa\nb"), ("a\nb".to_string(), None));
+        assert_eq!(decode("çay".as_bytes()), ("çay".to_string(), None));
+        assert_eq!(
+            decode(b"\xE7ay"),
+            ("çay".to_string(), Some("not valid UTF-8, decoded as Latin-1"))
+        );
+        assert_eq!(decode(b"\xFF\xFEa\x00\n\x00"), ("a\n".to_string(), None));
+        assert_eq!(decode(b"\xFE\xFF\x00a"), ("a".to_string(), None));
+        assert!(decode(b"\xFF\xFE\x00\xD8").1.is_some());
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        assert_eq!(
+            resolve_path(Path::new("/notes/sub"), "../x.org"),
+            PathBuf::from("/notes/x.org")
+        );
+        assert_eq!(
+            resolve_path(Path::new("/notes"), "./a/./b.md"),
+            PathBuf::from("/notes/a/b.md")
+        );
+        assert_eq!(resolve_path(Path::new("/notes"), "/abs.org"), PathBuf::from("/abs.org"));
     }
 }